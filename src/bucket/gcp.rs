@@ -1,17 +1,16 @@
+use super::{keys, Object, ObjectDetails};
 use crate::{
     errors::JobRunnerError::{
-        BucketCredentialsNotFoundError, DownloadFromBucketError, InvalidPathError,
-        ListFilesInBucketError, UploadToBucketError,
+        BucketCredentialsNotFoundError, CopyWithinBucketError, DeleteFromBucketError,
+        DownloadFromBucketError, ListFilesInBucketError, UploadToBucketError,
     },
-    CloudServiceProvider, PathKeyInConfig, Result,
+    CloudServiceProvider, Result,
 };
 use async_trait::async_trait;
 use cloud_storage::{Client, ListRequest};
-use std::{
-    env, fs, io,
-    path::{Path, PathBuf},
-};
+use std::{env, fs, path::Path};
 use tokio_stream::StreamExt;
+use tracing::{instrument, Span};
 
 pub struct CloudStorageBucket<'a> {
     bucket_name: &'a str,
@@ -34,89 +33,66 @@ impl<'a> CloudStorageBucket<'a> {
 
 #[async_trait]
 impl super::Bucket for CloudStorageBucket<'_> {
-    async fn download_inputs(
-        &self,
-        path_to_remote_inputs: &Path,
-        path_to_local_inputs: &Path,
-    ) -> Result<()> {
-        // If path_to_remote_inputs points to a folder inside the bucket, only
-        // list the objects inside that folder; otherwise, list all objects in
-        // the bucket.
-        //
-        // https://github.com/rust-lang/rust/pull/31877#issuecomment-191901957
-        let lr = if path_to_remote_inputs.components().next().is_some() {
-            let path_to_remote_inputs_as_string = path_to_remote_inputs
-                .to_str()
-                .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?
-                .to_string();
-            ListRequest {
-                prefix: Some(path_to_remote_inputs_as_string),
-                ..Default::default()
-            }
-        } else {
-            ListRequest::default()
-        };
-        let mut object_list_stream = Box::pin(
-            self.client
-                .object()
-                .list(self.bucket_name, lr)
-                .await
-                .map_err(|err| ListFilesInBucketError {
-                    source: Box::new(err),
-                })?,
-        );
-
-        while let Some(object_list) = object_list_stream.next().await {
-            match object_list {
-                Ok(list) => {
-                    for object in list.items {
-                        if !is_object_a_directory(&object.name) {
-                            self.download_object(
-                                &object.name,
-                                path_to_remote_inputs,
-                                path_to_local_inputs,
-                            )
-                            .await?;
-                        }
-                    }
-                }
-                // TODO: Does this situation deserve it's own error type? I got
-                // lazy here and just reused the one we already had for listing
-                // objects.
-                Err(err) => {
-                    return Err(ListFilesInBucketError {
-                        source: Box::new(err),
-                    })
-                }
-            }
-        }
-        Ok(())
+    #[instrument(
+        skip_all,
+        err,
+        fields(bucket = self.bucket_name, prefix = prefix, num_objects)
+    )]
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<Object>> {
+        let objects: Vec<Object> = self
+            .list(prefix)
+            .await?
+            .into_iter()
+            .map(|object| Object {
+                name: object.name,
+                generation: object.generation,
+            })
+            .collect();
+        Span::current().record("num_objects", objects.len());
+        Ok(objects)
     }
 
-    async fn upload_outputs(
-        &self,
-        path_to_local_outputs: &Path,
-        path_to_remote_outputs: &Path,
-    ) -> Result<()> {
-        for file_path in find_all_files(path_to_local_outputs)? {
-            self.upload_object(&file_path, path_to_local_outputs, path_to_remote_outputs)
-                .await?;
-        }
-        Ok(())
+    #[instrument(
+        skip_all,
+        err,
+        fields(bucket = self.bucket_name, prefix = prefix, num_objects)
+    )]
+    async fn list_object_details(&self, prefix: &str) -> Result<Vec<ObjectDetails>> {
+        let objects: Vec<ObjectDetails> = self
+            .list(prefix)
+            .await?
+            .into_iter()
+            .map(|object| ObjectDetails {
+                name: object.name,
+                size: object.size,
+                updated: object.updated,
+                content_type: object.content_type,
+            })
+            .collect();
+        Span::current().record("num_objects", objects.len());
+        Ok(objects)
     }
-}
 
-impl<'a> CloudStorageBucket<'a> {
     /// Downloads an object's contents from GCS, and writes it to disk in the
     /// provided `path_to_local_inputs`.
     ///
     /// Creates `path_to_local_inputs` if it doesn't already exist.
+    #[instrument(
+        skip_all,
+        err,
+        fields(bucket = self.bucket_name, object = remote_file_path, num_bytes)
+    )]
     async fn download_object(
         &self,
         remote_file_path: &str,
         path_to_remote_inputs: &Path,
         path_to_local_inputs: &Path,
-    ) -> Result<()> {
+    ) -> Result<u64> {
+        let local_file_path = keys::local_path(
+            remote_file_path,
+            path_to_remote_inputs,
+            path_to_local_inputs,
+        )?;
         let contents = self
             .client
             .object()
@@ -126,124 +102,162 @@ impl<'a> CloudStorageBucket<'a> {
                 source: Box::new(err),
             })?;
 
-        // TODO: Is this the right error we should be returning? Feels kinda
-        // tightly coupled to our config. I feel like `download_object()`
-        // shouldn't know that these Paths came from a config file.
-        let path_to_local_inputs_as_string = path_to_local_inputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::LocalInputs))?;
-        let mut local_file_path_as_string =
-            format!("{}/{}", path_to_local_inputs_as_string, remote_file_path);
-        if path_to_remote_inputs.components().next().is_some() {
-            // TODO: Is this the right error we should be returning? Feels kinda
-            // tightly coupled to our config. I feel like `download_object()`
-            // shouldn't know that these Paths came from a config file.
-            let path_to_remote_inputs_as_string = path_to_remote_inputs
-                .to_str()
-                .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
-            // TODO: Is there a better way to do this than to go from PathBufs
-            // to Strings and back to a PathBuf?
-            local_file_path_as_string = remote_file_path.replace(
-                path_to_remote_inputs_as_string,
-                path_to_local_inputs_as_string,
-            );
-        }
-        let local_file_path = Path::new(&local_file_path_as_string);
-
         // If the file lives inside a directory (or directories), make those.
         if let Some(local_file_dir) = local_file_path.parent() {
             fs::create_dir_all(local_file_dir)?;
         }
-        fs::write(local_file_path, contents)?;
+        let num_bytes = contents.len() as u64;
+        Span::current().record("num_bytes", num_bytes);
+        fs::write(&local_file_path, contents)?;
+        Ok(num_bytes)
+    }
+
+    #[instrument(
+        skip_all,
+        err,
+        fields(bucket = self.bucket_name, object = object_name, num_bytes = contents.len())
+    )]
+    async fn write_object(
+        &self,
+        object_name: &str,
+        contents: Vec<u8>,
+        mime_type: &str,
+    ) -> Result<()> {
+        self.client
+            .object()
+            .create(self.bucket_name, contents, object_name, mime_type)
+            .await
+            .map_err(|err| UploadToBucketError {
+                source: Box::new(err),
+            })?;
         Ok(())
     }
 
+    #[instrument(skip(self), err, fields(bucket = self.bucket_name))]
+    async fn copy_object(&self, from: &str, to: &str) -> Result<()> {
+        let object = self
+            .client
+            .object()
+            .read(self.bucket_name, from)
+            .await
+            .map_err(|err| CopyWithinBucketError {
+                source: Box::new(err),
+            })?;
+        self.client
+            .object()
+            .copy(&object, self.bucket_name, to)
+            .await
+            .map_err(|err| CopyWithinBucketError {
+                source: Box::new(err),
+            })?;
+        Ok(())
+    }
+
+    #[instrument(skip(self), err, fields(bucket = self.bucket_name, object = object_name))]
+    async fn delete_object(&self, object_name: &str) -> Result<()> {
+        self.client
+            .object()
+            .delete(self.bucket_name, object_name)
+            .await
+            .map_err(|err| DeleteFromBucketError {
+                source: Box::new(err),
+            })
+    }
+
+    #[instrument(
+        skip_all,
+        err,
+        fields(bucket = self.bucket_name, file = %local_file_path.display(), object, num_bytes)
+    )]
     async fn upload_object(
         &self,
         local_file_path: &Path,
         path_to_local_outputs: &Path,
         path_to_remote_outputs: &Path,
-    ) -> Result<()> {
-        // TODO: Return an error with more context instead of panicking.
-        // Something like: your operating system supports file paths that are
-        // not valid unicode, and we can't deal lol.
-        let local_file_path_as_string = local_file_path
-            .to_str()
-            .expect("csjr doesn't support paths that contain invalid unicode");
-        // TODO: Are these the right errors we should be returning? Feels kinda
-        // tightly coupled to our config. I feel like `download_object()`
-        // shouldn't know that these Paths came from a config file.
-        let path_to_local_outputs_as_string = path_to_local_outputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::LocalOutputs))?;
-        let path_to_remote_outputs_as_string = path_to_remote_outputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
-        // TODO: Is there a better way to do this than to go from PathBufs to
-        // Strings?
-        let remote_file_path_as_string = local_file_path_as_string.replace(
-            path_to_local_outputs_as_string,
-            path_to_remote_outputs_as_string,
-        );
+    ) -> Result<u64> {
+        let remote_file_path = keys::object_name(
+            local_file_path,
+            path_to_local_outputs,
+            path_to_remote_outputs,
+        )?;
+        Span::current().record("object", remote_file_path.as_str());
 
-        let contents = fs::read(&local_file_path)?;
-        let mime_type = mime_guess::from_path(&local_file_path)
+        let contents = fs::read(local_file_path)?;
+        let num_bytes = contents.len() as u64;
+        Span::current().record("num_bytes", num_bytes);
+        let mime_type = mime_guess::from_path(local_file_path)
             .first_or_octet_stream()
             .to_string();
         self.client
             .object()
-            .create(
-                self.bucket_name,
-                contents,
-                &remote_file_path_as_string,
-                &mime_type,
-            )
+            .create(self.bucket_name, contents, &remote_file_path, &mime_type)
             .await
             .map_err(|err| UploadToBucketError {
                 source: Box::new(err),
             })?;
-        Ok(())
+        Ok(num_bytes)
     }
 }
 
-fn is_object_a_directory(name: &str) -> bool {
-    name.ends_with('/')
-}
-
-#[cfg(test)]
-mod is_object_a_directory_tests {
-    #[test]
-    fn valid_dir() {
-        assert!(super::is_object_a_directory("/"));
-        assert!(super::is_object_a_directory("foo/"));
-        assert!(super::is_object_a_directory("foo/bar/"));
-    }
-
-    #[test]
-    fn valid_object() {
-        assert!(!super::is_object_a_directory("foo.txt"));
-        assert!(!super::is_object_a_directory("foo/bar.txt"));
-        assert!(!super::is_object_a_directory("foo/bar/baz.txt"));
-        assert!(!super::is_object_a_directory("foo"));
-        assert!(!super::is_object_a_directory("foo/bar"));
-        assert!(!super::is_object_a_directory("foo/bar/baz"));
-    }
-}
+impl<'a> CloudStorageBucket<'a> {
+    /// Lists all of the objects in the bucket that live inside the `prefix`
+    /// directory, leaving out directory placeholder objects.
+    async fn list(&self, prefix: &str) -> Result<Vec<cloud_storage::Object>> {
+        // If prefix points to a folder inside the bucket, only list the
+        // objects inside that folder; otherwise, list all objects in the
+        // bucket.
+        //
+        // https://github.com/rust-lang/rust/pull/31877#issuecomment-191901957
+        let lr = if Path::new(prefix).components().next().is_some() {
+            ListRequest {
+                prefix: Some(prefix.to_string()),
+                ..Default::default()
+            }
+        } else {
+            ListRequest::default()
+        };
+        let mut object_list_stream = Box::pin(
+            self.client
+                .object()
+                .list(self.bucket_name, lr)
+                .await
+                .map_err(|err| ListFilesInBucketError {
+                    source: Box::new(err),
+                })?,
+        );
 
-fn find_all_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-    if dir.is_dir() {
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                files.extend(find_all_files(&path)?);
-            } else {
-                files.push(path);
+        let mut objects = Vec::new();
+        while let Some(object_list) = object_list_stream.next().await {
+            match object_list {
+                Ok(list) => {
+                    // GCS matches prefixes character by character, so a
+                    // prefix of "img" also lists objects in "images/".
+                    // Objects with ".." in their names are kept, so that
+                    // downloading them fails loudly.
+                    for object in list.items {
+                        if !is_object_a_directory(&object.name)
+                            && keys::is_listed_inside(&object.name, Path::new(prefix))
+                        {
+                            objects.push(object);
+                        }
+                    }
+                }
+                // TODO: Does this situation deserve it's own error type? I got
+                // lazy here and just reused the one we already had for listing
+                // objects.
+                Err(err) => {
+                    return Err(ListFilesInBucketError {
+                        source: Box::new(err),
+                    })
+                }
             }
         }
+        Ok(objects)
     }
-    Ok(files)
+}
+
+fn is_object_a_directory(name: &str) -> bool {
+    name.ends_with('/')
 }
 
 /// Verifies that credentials for a Google Cloud service account are present
@@ -286,3 +300,23 @@ fn is_valid_json(contents: &str) -> bool {
     // TODO: Revisit. This is pretty scrappy LOL.
     contents.starts_with('{') && contents.ends_with('}') && contents.is_ascii()
 }
+
+#[cfg(test)]
+mod is_object_a_directory_tests {
+    #[test]
+    fn valid_dir() {
+        assert!(super::is_object_a_directory("/"));
+        assert!(super::is_object_a_directory("foo/"));
+        assert!(super::is_object_a_directory("foo/bar/"));
+    }
+
+    #[test]
+    fn valid_object() {
+        assert!(!super::is_object_a_directory("foo.txt"));
+        assert!(!super::is_object_a_directory("foo/bar.txt"));
+        assert!(!super::is_object_a_directory("foo/bar/baz.txt"));
+        assert!(!super::is_object_a_directory("foo"));
+        assert!(!super::is_object_a_directory("foo/bar"));
+        assert!(!super::is_object_a_directory("foo/bar/baz"));
+    }
+}
@@ -0,0 +1,285 @@
+//! Maps between the names of objects in a bucket and paths on disk.
+//!
+//! Object names are treated as `/`-separated paths, and prefixes match whole
+//! components only, so the prefix `img` contains `img/cat.png`, but not
+//! `images/cat.png`. `.` components are ignored, and a leading `/` doesn't
+//! count as a component.
+
+use crate::{
+    errors::JobRunnerError::{InvalidObjectNameError, NonUnicodeFileNameError},
+    Result,
+};
+use std::{
+    ffi::OsStr,
+    io,
+    path::{Component, Path, PathBuf},
+};
+
+/// Returns whether the object named `object_name` lives inside `prefix`. An
+/// object is inside its own name, and every object is inside an empty
+/// prefix.
+pub fn is_inside(object_name: &str, prefix: &Path) -> bool {
+    relative_components(Path::new(object_name), prefix).is_some()
+}
+
+/// Like [is_inside], but only looks at the components of `object_name` that
+/// come before its first `..` component, if it has one. Used when listing
+/// objects, so that an object whose name would escape `prefix` on disk (like
+/// `img/../../.bashrc`) still gets listed, and [local_path] refuses to save
+/// it, instead of it being silently left out.
+pub fn is_listed_inside(object_name: &str, prefix: &Path) -> bool {
+    let before_parent_dir: PathBuf = Path::new(object_name)
+        .components()
+        .take_while(|component| *component != Component::ParentDir)
+        .collect();
+    relative_components(&before_parent_dir, prefix).is_some()
+}
+
+/// Returns where the object named `object_name`, which lives inside
+/// `path_to_remote_inputs`, should be saved inside `path_to_local_inputs`.
+///
+/// # Errors
+///
+/// Returns an [InvalidObjectNameError] if the object isn't inside
+/// `path_to_remote_inputs`, or if its name has a `..` component that would
+/// put it somewhere else on disk.
+pub fn local_path(
+    object_name: &str,
+    path_to_remote_inputs: &Path,
+    path_to_local_inputs: &Path,
+) -> Result<PathBuf> {
+    let invalid = || InvalidObjectNameError {
+        object_name: object_name.into(),
+        prefix: path_to_remote_inputs.into(),
+    };
+    let components =
+        relative_components(Path::new(object_name), path_to_remote_inputs).ok_or_else(invalid)?;
+    if components.is_empty() {
+        return Err(invalid());
+    }
+    let mut local_path = path_to_local_inputs.to_path_buf();
+    local_path.extend(components);
+    Ok(local_path)
+}
+
+/// Returns the name of the object that the file at `local_file_path`, which
+/// lives inside `path_to_local_outputs`, should be uploaded as inside
+/// `path_to_remote_outputs`. Components are always separated by `/`.
+///
+/// # Errors
+///
+/// Returns a [NonUnicodeFileNameError] if the object's name wouldn't be valid
+/// UTF-8, or an `io::Error` if the file isn't inside `path_to_local_outputs`,
+/// or if `path_to_remote_outputs` has a `..` component.
+pub fn object_name(
+    local_file_path: &Path,
+    path_to_local_outputs: &Path,
+    path_to_remote_outputs: &Path,
+) -> Result<String> {
+    let not_inside = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{:?} can't be uploaded to {:?}, because it isn't inside {:?}",
+                local_file_path, path_to_remote_outputs, path_to_local_outputs
+            ),
+        )
+    };
+    let prefix = normal_components(path_to_remote_outputs).ok_or_else(not_inside)?;
+    let relative = local_file_path
+        .strip_prefix(path_to_local_outputs)
+        .ok()
+        .and_then(normal_components)
+        .ok_or_else(not_inside)?;
+    let names = prefix
+        .into_iter()
+        .chain(relative)
+        .map(OsStr::to_str)
+        .collect::<Option<Vec<_>>>()
+        .ok_or_else(|| NonUnicodeFileNameError {
+            path: local_file_path.into(),
+        })?;
+    Ok(names.join("/"))
+}
+
+/// Returns `path` with `.` components and any root left out, so that it can
+/// be compared with (and have its prefix stripped from) object names. Returns
+/// `None` if `path` has a `..` component.
+pub(crate) fn normalize(path: &Path) -> Option<PathBuf> {
+    normal_components(path).map(|components| components.into_iter().collect())
+}
+
+/// Returns the components of `path` that come after `prefix`, or `None` if
+/// `path` isn't inside `prefix`, or either has a `..` component.
+fn relative_components<'a>(path: &'a Path, prefix: &Path) -> Option<Vec<&'a OsStr>> {
+    let path = normal_components(path)?;
+    let prefix = normal_components(prefix)?;
+    if path.len() < prefix.len() || path[..prefix.len()] != prefix[..] {
+        return None;
+    }
+    Some(path[prefix.len()..].to_vec())
+}
+
+/// Returns the names of each directory and file in `path`, leaving out `.`
+/// and any root, or `None` if `path` has a `..` component.
+fn normal_components(path: &Path) -> Option<Vec<&OsStr>> {
+    let mut components = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => components.push(name),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    Some(components)
+}
+
+#[cfg(test)]
+mod keys_tests {
+    use super::{is_inside, is_listed_inside, local_path, normalize, object_name};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn prefix_matches_whole_components() {
+        assert!(is_inside("img/cat.png", Path::new("img")));
+        assert!(!is_inside("images/cat.png", Path::new("img")));
+        assert!(!is_inside("img", Path::new("img/cat.png")));
+    }
+
+    #[test]
+    fn object_with_parent_dir_is_listed_but_not_saved() {
+        assert!(!is_inside("img/../../.bashrc", Path::new("img")));
+        assert!(is_listed_inside("img/../../.bashrc", Path::new("img")));
+        assert!(is_listed_inside("../.bashrc", Path::new("")));
+        assert!(!is_listed_inside("images/../.bashrc", Path::new("img")));
+        assert!(local_path("img/../../.bashrc", Path::new("img"), Path::new("in")).is_err());
+    }
+
+    #[test]
+    fn object_is_inside_its_own_name() {
+        assert!(is_inside("out/_SUCCESS", Path::new("out/_SUCCESS")));
+    }
+
+    #[test]
+    fn trailing_slashes_are_ignored() {
+        assert!(is_inside("img/cat.png", Path::new("img/")));
+        assert_eq!(
+            local_path("img/cat.png", Path::new("img/"), Path::new("in/")).unwrap(),
+            PathBuf::from("in/cat.png")
+        );
+        assert_eq!(
+            object_name(
+                Path::new("out/cat.png"),
+                Path::new("out/"),
+                Path::new("results/")
+            )
+            .unwrap(),
+            "results/cat.png"
+        );
+    }
+
+    #[test]
+    fn empty_prefix_contains_everything() {
+        assert!(is_inside("cat.png", Path::new("")));
+        assert_eq!(
+            local_path("img/cat.png", Path::new(""), Path::new("in")).unwrap(),
+            PathBuf::from("in/img/cat.png")
+        );
+        assert_eq!(
+            object_name(Path::new("out/cat.png"), Path::new("out"), Path::new("")).unwrap(),
+            "cat.png"
+        );
+    }
+
+    #[test]
+    fn nested_dirs_are_kept() {
+        assert_eq!(
+            local_path("img/2022/04/cat.png", Path::new("img"), Path::new("in")).unwrap(),
+            PathBuf::from("in/2022/04/cat.png")
+        );
+        assert_eq!(
+            object_name(
+                Path::new("out/2022/04/cat.png"),
+                Path::new("out"),
+                Path::new("results/resized")
+            )
+            .unwrap(),
+            "results/resized/2022/04/cat.png"
+        );
+    }
+
+    #[test]
+    fn prefix_is_only_stripped_from_the_start() {
+        assert_eq!(
+            local_path("img/img/cat.png", Path::new("img"), Path::new("in")).unwrap(),
+            PathBuf::from("in/img/cat.png")
+        );
+        assert_eq!(
+            object_name(
+                Path::new("out/out/cat.png"),
+                Path::new("out"),
+                Path::new("img")
+            )
+            .unwrap(),
+            "img/out/cat.png"
+        );
+    }
+
+    #[test]
+    fn current_dir_and_leading_slash_are_ignored() {
+        assert_eq!(
+            local_path("/img/./cat.png", Path::new("./img"), Path::new("in")).unwrap(),
+            PathBuf::from("in/cat.png")
+        );
+        assert_eq!(
+            object_name(
+                Path::new("out/cat.png"),
+                Path::new("out"),
+                Path::new("/./results")
+            )
+            .unwrap(),
+            "results/cat.png"
+        );
+        assert_eq!(
+            normalize(Path::new("/./results/")),
+            Some(PathBuf::from("results"))
+        );
+        assert_eq!(normalize(Path::new("results/../secrets")), None);
+    }
+
+    #[test]
+    fn local_outputs_can_be_in_parent_dir() {
+        assert_eq!(
+            object_name(
+                Path::new("../out/cat.png"),
+                Path::new("../out"),
+                Path::new("results")
+            )
+            .unwrap(),
+            "results/cat.png"
+        );
+    }
+
+    #[test]
+    fn parent_dir_is_rejected() {
+        assert!(!is_inside("img/../secrets.txt", Path::new("img")));
+        assert!(local_path("img/../../secrets.txt", Path::new("img"), Path::new("in")).is_err());
+        assert!(local_path("img/cat.png", Path::new("img/.."), Path::new("in")).is_err());
+        assert!(object_name(Path::new("out/cat.png"), Path::new("out"), Path::new("..")).is_err());
+    }
+
+    #[test]
+    fn object_outside_prefix_is_rejected() {
+        assert!(local_path("images/cat.png", Path::new("img"), Path::new("in")).is_err());
+        assert!(local_path("img", Path::new("img"), Path::new("in")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_unicode_file_name_is_rejected() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let local_file_path = Path::new("out").join(OsStr::from_bytes(b"caf\xe9.png"));
+        assert!(object_name(&local_file_path, Path::new("out"), Path::new("results")).is_err());
+    }
+}
@@ -1,35 +1,191 @@
 pub mod gcp;
+pub mod keys;
 
-use crate::Result;
+use crate::{
+    errors::JobRunnerError::InvalidPathError,
+    pretty_print::{self, Verbosity},
+    PathKeyInConfig, Result,
+};
 use async_trait::async_trait;
-use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tracing::instrument;
+
+/// An object (a file) that lives in a cloud storage bucket.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Object {
+    /// The object's full name (its key) inside the bucket.
+    pub name: String,
+    /// Identifies a particular version of the object. Overwriting an object
+    /// gives it a new generation.
+    pub generation: i64,
+}
+
+/// What a cloud storage bucket knows about an object, beyond what's needed to
+/// tell it apart from other objects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectDetails {
+    /// The object's full name (its key) inside the bucket.
+    pub name: String,
+    /// How big the object's contents are, in bytes.
+    pub size: u64,
+    /// When the object was last modified.
+    pub updated: DateTime<Utc>,
+    /// The object's MIME type, if it has one.
+    pub content_type: Option<String>,
+}
 
 #[async_trait]
-pub trait Bucket {
+pub trait Bucket: Sync {
+    /// Lists all of the objects in a cloud storage bucket that live inside the
+    /// `prefix` directory (see [keys::is_listed_inside]). Directory
+    /// placeholder objects are left out.
+    ///
+    /// If `prefix` is empty, lists every object in the bucket.
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<Object>>;
+
+    /// Like [Bucket::list_objects], but returns the [ObjectDetails] of each
+    /// object.
+    async fn list_object_details(&self, prefix: &str) -> Result<Vec<ObjectDetails>>;
+
+    /// Downloads the object named `object_name` from a cloud storage bucket,
+    /// and saves it on disk inside `path_to_local_inputs`, keeping its path
+    /// relative to `path_to_remote_inputs`. Returns the number of bytes
+    /// downloaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the directory the object should be saved in
+    /// doesn't exist on disk, and this function fails to create it, or an
+    /// [InvalidObjectNameError](crate::JobRunnerError::InvalidObjectNameError)
+    /// if the object isn't inside `path_to_remote_inputs` (see
+    /// [keys::local_path]).
+    async fn download_object(
+        &self,
+        object_name: &str,
+        path_to_remote_inputs: &Path,
+        path_to_local_inputs: &Path,
+    ) -> Result<u64>;
+
     /// Downloads the `path_to_remote_inputs` directory, and all its contents,
     /// from a cloud storage bucket, and saves them on disk at
-    /// `path_to_local_inputs`.
+    /// `path_to_local_inputs`. Returns the number of bytes downloaded.
     ///
     /// # Errors
     ///
     /// Returns an `io::Error` if the `path_to_local_inputs` directory doesn't
-    /// exist on disk, and this function fails to create it.
+    /// exist on disk, and this function fails to create it, or an
+    /// [InvalidPathError](crate::JobRunnerError::InvalidPathError) if
+    /// `path_to_remote_inputs` isn't valid UTF-8.
+    #[instrument(skip_all, fields(prefix = %path_to_remote_inputs.display()))]
     async fn download_inputs(
         &self,
         path_to_remote_inputs: &Path,
         path_to_local_inputs: &Path,
+    ) -> Result<u64> {
+        let prefix = path_to_remote_inputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+        let objects = self.list_objects(prefix).await?;
+        let mut progress = pretty_print::transfer_progress("Downloading", objects.len());
+        let mut num_bytes = 0;
+        for object in objects {
+            let object_num_bytes = self
+                .download_object(&object.name, path_to_remote_inputs, path_to_local_inputs)
+                .await?;
+            progress.advance(object_num_bytes);
+            num_bytes += object_num_bytes;
+        }
+        progress.finish();
+        Ok(num_bytes)
+    }
+
+    /// Creates an object named `object_name` with the provided `contents`, or
+    /// overwrites it if it already exists.
+    async fn write_object(
+        &self,
+        object_name: &str,
+        contents: Vec<u8>,
+        mime_type: &str,
     ) -> Result<()>;
 
+    /// Copies the object named `from` to a new object named `to` in the same
+    /// bucket, overwriting `to` if it already exists.
+    async fn copy_object(&self, from: &str, to: &str) -> Result<()>;
+
+    /// Deletes the object named `object_name`.
+    async fn delete_object(&self, object_name: &str) -> Result<()>;
+
+    /// Uploads the file at `local_file_path` to a cloud storage bucket,
+    /// keeping its path relative to `path_to_local_outputs` inside
+    /// `path_to_remote_outputs` (see [keys::object_name]). Returns the number
+    /// of bytes uploaded.
+    async fn upload_object(
+        &self,
+        local_file_path: &Path,
+        path_to_local_outputs: &Path,
+        path_to_remote_outputs: &Path,
+    ) -> Result<u64>;
+
     /// Uploads the `path_to_local_outputs` directory, and all its contents,
-    /// on disk to a cloud storage bucket at `path_to_remote_outputs`.
+    /// on disk to a cloud storage bucket at `path_to_remote_outputs`. Returns
+    /// the number of bytes uploaded.
     ///
     /// # Errors
     ///
-    /// Returns an `io::Error` if the `path_to_local_outputs` directory doesn't
-    /// exist on disk.
+    /// Returns an `io::Error` if the contents of the `path_to_local_outputs`
+    /// directory can't be read.
+    #[instrument(
+        skip_all,
+        fields(
+            from = %path_to_local_outputs.display(),
+            to = %path_to_remote_outputs.display(),
+        )
+    )]
     async fn upload_outputs(
         &self,
         path_to_local_outputs: &Path,
         path_to_remote_outputs: &Path,
-    ) -> Result<()>;
+    ) -> Result<u64> {
+        let file_paths = find_all_files(path_to_local_outputs)?;
+        let mut progress = pretty_print::transfer_progress("Uploading", file_paths.len());
+        let mut num_bytes = 0;
+        for file_path in file_paths {
+            let file_num_bytes = self
+                .upload_object(&file_path, path_to_local_outputs, path_to_remote_outputs)
+                .await?;
+            progress.advance(file_num_bytes);
+            pretty_print::status_at(
+                Verbosity::Verbose,
+                "Uploaded",
+                &format!("\"{}\"", file_path.display()),
+                true,
+            )?;
+            num_bytes += file_num_bytes;
+        }
+        progress.finish();
+        Ok(num_bytes)
+    }
+}
+
+/// Returns the path to every file inside `dir`, and inside the directories
+/// inside it. Returns an empty list if `dir` doesn't exist.
+pub(crate) fn find_all_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_dir() {
+                files.extend(find_all_files(&path)?);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
 }
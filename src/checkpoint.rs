@@ -0,0 +1,348 @@
+use crate::{
+    bucket::Object,
+    errors::JobRunnerError::{InvalidRunIdError, RunNotFoundError, StateFileError},
+    Result,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Something that a run got done, as recorded in its checkpoint file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Entry {
+    /// The run started processing `objects`.
+    Started {
+        job: String,
+        started_at: DateTime<Utc>,
+        objects: Vec<Object>,
+    },
+    /// The object named `object` was downloaded.
+    Downloaded { object: String },
+    /// The run's outputs go to `path_to_remote_outputs`, with its run
+    /// identifiers filled in.
+    OutputsScoped { path_to_remote_outputs: String },
+    /// The step at `index` (starting at 0) completed successfully.
+    StepCompleted { index: usize },
+    /// A local output was uploaded as the object named `object`.
+    Uploaded { object: String },
+}
+
+/// A record of what a run of a job has gotten done so far, so that if the run
+/// is interrupted or fails, it can be resumed where it stopped instead of
+/// starting over.
+///
+/// Persisted on disk as a JSON-lines file named after the run's ID, with one
+/// entry for each thing the run gets done. The file is removed once the run
+/// succeeds.
+#[derive(Debug, Default)]
+pub struct Checkpoint {
+    /// Where this checkpoint is persisted. If `None`, the checkpoint only
+    /// lives in memory.
+    path: Option<PathBuf>,
+    run_id: String,
+    job: String,
+    /// The objects the run is processing, once it's started.
+    objects: Option<Vec<Object>>,
+    downloaded: HashSet<String>,
+    path_to_remote_outputs: Option<String>,
+    completed_steps: HashSet<usize>,
+    uploaded: HashSet<String>,
+}
+
+impl Checkpoint {
+    /// Returns a new checkpoint for a run of the job named `job_name`, to be
+    /// persisted in `path_to_runs`. Nothing is written until the run starts.
+    pub fn create(path_to_runs: &Path, job_name: &str) -> Self {
+        // Job names can have characters in them that run IDs can't, like
+        // `/`, so those are replaced. The checkpoint still records the job's
+        // real name.
+        let safe_job_name: String = job_name
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let run_id = format!(
+            "{}-{}-{safe_job_name}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            process::id()
+        );
+        Self {
+            path: Some(path_to_runs.join(format!("{run_id}.jsonl"))),
+            run_id,
+            job: job_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Reads the checkpoint of the run with the ID `run_id` from
+    /// `path_to_runs`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [InvalidRunIdError] if `run_id` could point outside of
+    /// `path_to_runs`, or a [RunNotFoundError] if there's no checkpoint for
+    /// that run, either because it never started, or because it already
+    /// succeeded.
+    pub fn open(path_to_runs: &Path, run_id: &str) -> Result<Self> {
+        if run_id.is_empty() || run_id.contains(['/', '\\']) || run_id.contains("..") {
+            return Err(InvalidRunIdError {
+                run_id: run_id.into(),
+            });
+        }
+        let path = path_to_runs.join(format!("{run_id}.jsonl"));
+        if !path.exists() {
+            return Err(RunNotFoundError {
+                run_id: run_id.into(),
+            });
+        }
+        let mut checkpoint = Self {
+            path: Some(path.clone()),
+            run_id: run_id.into(),
+            ..Default::default()
+        };
+        for line in fs::read_to_string(&path)?.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(line).map_err(|err| StateFileError {
+                path: path.clone(),
+                source: Box::new(err),
+            })?;
+            checkpoint.apply(entry);
+        }
+        if checkpoint.objects.is_none() {
+            return Err(RunNotFoundError {
+                run_id: run_id.into(),
+            });
+        }
+        Ok(checkpoint)
+    }
+
+    /// Returns the ID of the run this checkpoint is for.
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Returns the name of the job that the run this checkpoint is for runs.
+    pub fn job(&self) -> &str {
+        &self.job
+    }
+
+    /// Returns the objects the run is processing, or `None` if it hasn't
+    /// started yet.
+    pub fn objects(&self) -> Option<&[Object]> {
+        self.objects.as_deref()
+    }
+
+    /// Returns true if the run has started, and its checkpoint file has been
+    /// written, so that it can be resumed.
+    pub fn is_resumable(&self) -> bool {
+        self.path.is_some() && self.objects.is_some()
+    }
+
+    /// Returns true if the object named `object_name` has been downloaded.
+    pub fn is_downloaded(&self, object_name: &str) -> bool {
+        self.downloaded.contains(object_name)
+    }
+
+    /// Returns where the run's outputs go, if that's been decided.
+    pub fn path_to_remote_outputs(&self) -> Option<&str> {
+        self.path_to_remote_outputs.as_deref()
+    }
+
+    /// Returns true if the step at `index` (starting at 0) has completed.
+    pub fn is_step_completed(&self, index: usize) -> bool {
+        self.completed_steps.contains(&index)
+    }
+
+    /// Returns true if a local output has been uploaded as the object named
+    /// `object_name`.
+    pub fn is_uploaded(&self, object_name: &str) -> bool {
+        self.uploaded.contains(object_name)
+    }
+
+    /// Records that the run started processing `objects`, unless it already
+    /// has (because it's being resumed).
+    pub fn record_start(&mut self, objects: &[Object]) -> Result<()> {
+        if self.objects.is_some() {
+            return Ok(());
+        }
+        self.record(Entry::Started {
+            job: self.job.clone(),
+            started_at: Utc::now(),
+            objects: objects.to_vec(),
+        })
+    }
+
+    /// Records that the object named `object_name` was downloaded.
+    pub fn record_download(&mut self, object_name: &str) -> Result<()> {
+        self.record(Entry::Downloaded {
+            object: object_name.into(),
+        })
+    }
+
+    /// Records that the run's outputs go to `path_to_remote_outputs`.
+    pub fn record_outputs_scoped(&mut self, path_to_remote_outputs: &str) -> Result<()> {
+        self.record(Entry::OutputsScoped {
+            path_to_remote_outputs: path_to_remote_outputs.into(),
+        })
+    }
+
+    /// Records that the step at `index` (starting at 0) completed.
+    pub fn record_step(&mut self, index: usize) -> Result<()> {
+        self.record(Entry::StepCompleted { index })
+    }
+
+    /// Records that a local output was uploaded as the object named
+    /// `object_name`.
+    pub fn record_upload(&mut self, object_name: &str) -> Result<()> {
+        self.record(Entry::Uploaded {
+            object: object_name.into(),
+        })
+    }
+
+    /// Removes the checkpoint file, once there's nothing left to resume.
+    pub fn remove(self) -> Result<()> {
+        match &self.path {
+            Some(path) if path.exists() => Ok(fs::remove_file(path)?),
+            _ => Ok(()),
+        }
+    }
+
+    /// Appends `entry` to the checkpoint file, and applies it. Creates the
+    /// checkpoint file, and any missing parent directories, if they don't
+    /// exist yet.
+    fn record(&mut self, entry: Entry) -> Result<()> {
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let line = serde_json::to_string(&entry).map_err(|err| StateFileError {
+                path: path.clone(),
+                source: Box::new(err),
+            })?;
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{line}")?;
+        }
+        self.apply(entry);
+        Ok(())
+    }
+
+    /// Updates this checkpoint with what `entry` says the run got done.
+    fn apply(&mut self, entry: Entry) {
+        match entry {
+            Entry::Started { job, objects, .. } => {
+                self.job = job;
+                self.objects = Some(objects);
+            }
+            Entry::Downloaded { object } => {
+                self.downloaded.insert(object);
+            }
+            Entry::OutputsScoped {
+                path_to_remote_outputs,
+            } => self.path_to_remote_outputs = Some(path_to_remote_outputs),
+            Entry::StepCompleted { index } => {
+                self.completed_steps.insert(index);
+            }
+            Entry::Uploaded { object } => {
+                self.uploaded.insert(object);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::Checkpoint;
+    use crate::{bucket::Object, JobRunnerError};
+    use std::fs;
+
+    #[test]
+    fn reopened_checkpoint_has_everything_recorded() {
+        let path_to_runs = std::env::temp_dir().join("csjr-checkpoint-tests-reopened");
+        let _ = fs::remove_dir_all(&path_to_runs);
+        let objects = vec![Object {
+            name: "img/cat.png".into(),
+            generation: 7,
+        }];
+        let mut checkpoint = Checkpoint::create(&path_to_runs, "resize");
+        checkpoint.record_start(&objects).unwrap();
+        checkpoint.record_download("img/cat.png").unwrap();
+        checkpoint
+            .record_outputs_scoped("out/20220418T020000Z")
+            .unwrap();
+        checkpoint.record_step(0).unwrap();
+        checkpoint
+            .record_upload("out/20220418T020000Z/cat.png")
+            .unwrap();
+
+        let reopened = Checkpoint::open(&path_to_runs, checkpoint.run_id()).unwrap();
+
+        assert_eq!(reopened.job(), "resize");
+        assert_eq!(reopened.objects(), Some(&objects[..]));
+        assert!(reopened.is_downloaded("img/cat.png"));
+        assert_eq!(
+            reopened.path_to_remote_outputs(),
+            Some("out/20220418T020000Z")
+        );
+        assert!(reopened.is_step_completed(0));
+        assert!(!reopened.is_step_completed(1));
+        assert!(reopened.is_uploaded("out/20220418T020000Z/cat.png"));
+        assert!(reopened.is_resumable());
+        fs::remove_dir_all(&path_to_runs).unwrap();
+    }
+
+    #[test]
+    fn removed_checkpoint_is_not_found() {
+        let path_to_runs = std::env::temp_dir().join("csjr-checkpoint-tests-removed");
+        let _ = fs::remove_dir_all(&path_to_runs);
+        let mut checkpoint = Checkpoint::create(&path_to_runs, "resize");
+        checkpoint.record_start(&[]).unwrap();
+        let run_id = checkpoint.run_id().to_string();
+
+        checkpoint.remove().unwrap();
+
+        assert!(matches!(
+            Checkpoint::open(&path_to_runs, &run_id),
+            Err(JobRunnerError::RunNotFoundError { .. })
+        ));
+        fs::remove_dir_all(&path_to_runs).unwrap();
+    }
+
+    #[test]
+    fn run_id_outside_of_runs_dir_is_invalid() {
+        let path_to_runs = std::env::temp_dir().join("csjr-checkpoint-tests-invalid");
+
+        for run_id in ["", "../ledger/resize", "runs/resize", "runs\\resize", ".."] {
+            assert!(
+                matches!(
+                    Checkpoint::open(&path_to_runs, run_id),
+                    Err(JobRunnerError::InvalidRunIdError { .. })
+                ),
+                "{run_id:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn checkpoint_in_memory_is_not_resumable() {
+        let mut checkpoint = Checkpoint::default();
+        checkpoint.record_start(&[]).unwrap();
+        checkpoint.record_step(0).unwrap();
+
+        assert!(checkpoint.is_step_completed(0));
+        assert!(!checkpoint.is_resumable());
+    }
+}
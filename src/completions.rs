@@ -0,0 +1,235 @@
+use std::io::{self, Write};
+use structopt::clap::{App, Shell};
+
+/// Bash function that prints the names of the jobs in the config file that's
+/// passed with `-c` (or `--config`) on the command line being completed.
+const BASH_JOB_NAMES: &str = r#"
+_{bin}_job_names() {
+    local i config
+    for (( i = 1; i < ${#COMP_WORDS[@]}; i++ )); do
+        case "${COMP_WORDS[i]}" in
+            -c|--config)
+                # Bash splits `--config=path` into three words.
+                if [[ "${COMP_WORDS[i+1]}" == "=" ]]; then
+                    config="${COMP_WORDS[i+2]}"
+                else
+                    config="${COMP_WORDS[i+1]}"
+                fi
+                ;;
+        esac
+    done
+    if [[ -n "${config}" ]]; then
+        {bin} --config "${config/#\~/$HOME}" jobs --names 2>/dev/null
+    else
+        {bin} jobs --names 2>/dev/null
+    fi
+}
+"#;
+
+/// Zsh function that completes the names of the jobs in the config file
+/// that's passed with `-c` (or `--config`) on the command line being
+/// completed.
+const ZSH_JOB_NAMES: &str = r#"(( $+functions[_{bin}_job_names] )) ||
+_{bin}_job_names() {
+    local i config
+    local -a job_names
+    for (( i = 1; i < ${#words}; i++ )); do
+        case "${words[i]}" in
+            -c|--config) config="${words[i+1]}" ;;
+            --config=*) config="${words[i]#--config=}" ;;
+        esac
+    done
+    if [[ -n "${config}" ]]; then
+        job_names=(${(f)"$({bin} --config "${config/#\~/$HOME}" jobs --names 2>/dev/null)"})
+    else
+        job_names=(${(f)"$({bin} jobs --names 2>/dev/null)"})
+    fi
+    _describe -t job-names 'job name' job_names
+}
+
+"#;
+
+/// Fish function that prints the names of the jobs in the config file that's
+/// passed with `-c` (or `--config`) on the command line being completed.
+const FISH_JOB_NAMES: &str = r#"
+function __{bin}_job_names
+    set -l tokens (commandline -opc)
+    set -l config
+    for i in (seq (count $tokens))
+        switch $tokens[$i]
+            case -c --config
+                set config $tokens[(math $i + 1)]
+            case '--config=*'
+                set config (string replace -- '--config=' '' $tokens[$i])
+        end
+    end
+    if test -n "$config"
+        {bin} --config (string replace -r -- '^~' $HOME $config) jobs --names 2>/dev/null
+    else
+        {bin} jobs --names 2>/dev/null
+    end
+end
+"#;
+
+/// Writes a script to `w` that completes `app`'s subcommands and options in
+/// `shell`, for the binary named `bin_name`.
+///
+/// In bash, zsh, and fish, job names are completed too, wherever `app` takes
+/// an argument named `job-name` (or `job-names`). They're read from the
+/// config file on the command line that's being completed, by running
+/// `<bin_name> jobs --names`, so they're always up to date.
+pub fn generate(mut app: App, shell: Shell, bin_name: &str, w: &mut impl Write) -> io::Result<()> {
+    let mut script = Vec::new();
+    app.gen_completions_to(bin_name, shell, &mut script);
+    let script =
+        String::from_utf8(script).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let script = match shell {
+        Shell::Bash => with_bash_job_names(&script, bin_name),
+        Shell::Zsh => with_zsh_job_names(&script, bin_name),
+        Shell::Fish => {
+            let mut bash_script = Vec::new();
+            app.gen_completions_to(bin_name, Shell::Bash, &mut bash_script);
+            let bash_script = String::from_utf8_lossy(&bash_script);
+            with_fish_job_names(&script, &bash_script, bin_name)
+        }
+        Shell::PowerShell | Shell::Elvish => script,
+    };
+    w.write_all(script.as_bytes())
+}
+
+/// Returns the bash `script` generated by clap with the `<job-name>`
+/// placeholders it offers as completions replaced with actual job names.
+fn with_bash_job_names(script: &str, bin_name: &str) -> String {
+    let job_names = format!("$(_{bin_name}_job_names)");
+    let script = script
+        .replace("<job-names>...", &job_names)
+        .replace("<job-name>", &job_names);
+    format!("{}{script}", BASH_JOB_NAMES.replace("{bin}", bin_name))
+}
+
+/// Returns the zsh `script` generated by clap with job-name arguments
+/// completed with job names instead of files.
+fn with_zsh_job_names(script: &str, bin_name: &str) -> String {
+    let action = format!(":_{bin_name}_job_names'");
+    let script: Vec<String> = script
+        .lines()
+        .map(|line| {
+            if (line.starts_with("':job-name") || line.starts_with("'::job-name"))
+                && line.ends_with(":_files' \\")
+            {
+                line.replace(":_files'", &action)
+            } else {
+                line.into()
+            }
+        })
+        .collect();
+    let script = script.join("\n");
+    let main_call = format!("_{bin_name} \"$@\"");
+    match script.rfind(&main_call) {
+        Some(i) => format!(
+            "{}{}{}",
+            &script[..i],
+            ZSH_JOB_NAMES.replace("{bin}", bin_name),
+            &script[i..]
+        ),
+        None => script,
+    }
+}
+
+/// Returns the fish `script` generated by clap with completions for job names
+/// added, wherever `bash_script`, which clap generated for the same app,
+/// offers its `<job-name>` placeholders as completions.
+fn with_fish_job_names(script: &str, bash_script: &str, bin_name: &str) -> String {
+    let mut script = format!("{script}{}", FISH_JOB_NAMES.replace("{bin}", bin_name));
+    let block_prefix = format!("{bin_name}__");
+    let mut subcommand = None;
+    let mut takes_job_name_at_top_level = false;
+    let mut subcommands_that_take_job_names = Vec::new();
+    for line in bash_script.lines().map(str::trim) {
+        if let Some(block) = line.strip_suffix(')') {
+            subcommand = if block == bin_name {
+                Some("")
+            } else {
+                block.strip_prefix(&block_prefix)
+            };
+        } else if line.starts_with("opts=") && line.contains("<job-name") {
+            match subcommand {
+                Some("") => takes_job_name_at_top_level = true,
+                Some(subcommand) => subcommands_that_take_job_names.push(subcommand),
+                None => {}
+            }
+        }
+    }
+    if takes_job_name_at_top_level {
+        script += &format!(
+            "complete -c {bin_name} -n \"__fish_use_subcommand\" -f -a \"(__{bin_name}_job_names)\"\n"
+        );
+    }
+    if !subcommands_that_take_job_names.is_empty() {
+        script += &format!(
+            "complete -c {bin_name} -n \"__fish_seen_subcommand_from {}\" -f -a \"(__{bin_name}_job_names)\"\n",
+            subcommands_that_take_job_names.join(" ")
+        );
+    }
+    script
+}
+
+#[cfg(test)]
+mod generate_tests {
+    use super::generate;
+    use structopt::clap::{App, Arg, Shell, SubCommand};
+
+    fn app() -> App<'static, 'static> {
+        App::new("csjr")
+            .arg(
+                Arg::with_name("config")
+                    .short("c")
+                    .long("config")
+                    .takes_value(true),
+            )
+            .arg(Arg::with_name("job-name"))
+            .subcommand(SubCommand::with_name("daemon"))
+            .subcommand(
+                SubCommand::with_name("show").arg(Arg::with_name("job-name").required(true)),
+            )
+            .subcommand(
+                SubCommand::with_name("watch").arg(Arg::with_name("job-names").multiple(true)),
+            )
+    }
+
+    fn script(shell: Shell) -> String {
+        let mut w = Vec::new();
+        generate(app(), shell, "csjr", &mut w).unwrap();
+        String::from_utf8(w).unwrap()
+    }
+
+    #[test]
+    fn bash_completes_job_names_instead_of_placeholders() {
+        let script = script(Shell::Bash);
+        assert!(script.contains("_csjr_job_names() {"));
+        assert!(script.contains("csjr --config \"${config/#\\~/$HOME}\" jobs --names"));
+        assert_eq!(script.matches("$(_csjr_job_names)").count(), 3);
+        assert!(!script.contains("<job-name"));
+    }
+
+    #[test]
+    fn zsh_completes_job_names_instead_of_files() {
+        let script = script(Shell::Zsh);
+        assert_eq!(script.matches(":_csjr_job_names' \\").count(), 3);
+        let helper = script.find("_csjr_job_names() {").unwrap();
+        let main_call = script.rfind("_csjr \"$@\"").unwrap();
+        assert!(helper < main_call);
+    }
+
+    #[test]
+    fn fish_completes_job_names_for_subcommands_that_take_them() {
+        let script = script(Shell::Fish);
+        assert!(script.contains("function __csjr_job_names"));
+        assert!(script.contains(
+            "complete -c csjr -n \"__fish_use_subcommand\" -f -a \"(__csjr_job_names)\"\n"
+        ));
+        assert!(script.contains(
+            "complete -c csjr -n \"__fish_seen_subcommand_from show watch\" -f -a \"(__csjr_job_names)\"\n"
+        ));
+    }
+}
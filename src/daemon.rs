@@ -0,0 +1,292 @@
+use crate::{
+    errors::JobRunnerError::{InterruptedError, InvalidScheduleError},
+    pretty_print, Job, JobRunner, Result,
+};
+use chrono::{DateTime, Local};
+use croner::Cron;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io,
+    sync::Arc,
+};
+use tokio::sync::Notify;
+
+impl<'a> JobRunner<'a> {
+    /// Stays resident, and runs each job that has a `schedule` whenever that
+    /// schedule says to.
+    ///
+    /// Jobs run one at a time, so a job never overlaps with another run of
+    /// itself. If a run takes so long that the job's next scheduled time has
+    /// already passed, that time is skipped. Jobs that fail are reported, and
+    /// then run again at their next scheduled time.
+    ///
+    /// # Errors
+    ///
+    /// On SIGTERM (or Ctrl-C), lets the step that's currently running finish,
+    /// and then returns an [InterruptedError], so that csjr exits with
+    /// [exit_code::TERMINATED](crate::exit_code::TERMINATED) (or
+    /// [exit_code::INTERRUPTED](crate::exit_code::INTERRUPTED)).
+    pub async fn daemon(&mut self) -> Result<()> {
+        let mut schedules = parse_schedules(&self.config.jobs, Local::now())?;
+        if schedules.is_empty() {
+            pretty_print::status("Finished", &"no jobs have a schedule", false)?;
+            return Ok(());
+        }
+
+        let stop_requested = Arc::clone(&self.options.stop_requested);
+        let woken = Arc::new(Notify::new());
+        let shutdown_signal = shutdown_signal()?;
+        {
+            let stop_requested = Arc::clone(&stop_requested);
+            let woken = Arc::clone(&woken);
+            tokio::spawn(async move {
+                let _ = stop_requested.set(shutdown_signal.await);
+                woken.notify_one();
+            });
+        }
+
+        loop {
+            if let Some(&signal) = stop_requested.get() {
+                return stop(signal);
+            }
+            let next = schedules
+                .iter()
+                .min_by_key(|schedule| schedule.next_run)
+                .expect("there's at least one scheduled job");
+            pretty_print::status(
+                "Waiting",
+                &format!(
+                    "to run {} at {}",
+                    next.job_name,
+                    next.next_run.format("%Y-%m-%d %H:%M:%S")
+                ),
+                false,
+            )?;
+            let wait = (next.next_run - Local::now()).to_std().unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = woken.notified() => {}
+            }
+
+            let now = Local::now();
+            for schedule in &mut schedules {
+                if let Some(&signal) = stop_requested.get() {
+                    return stop(signal);
+                }
+                if !schedule.is_due(now) {
+                    continue;
+                }
+                pretty_print::status("Running", &format!("{}...", schedule.job_name), false)?;
+                match self
+                    .run_job_without_checkpoint(schedule.job_name, None)
+                    .await
+                {
+                    Ok(()) | Err(InterruptedError { .. }) => {}
+                    Err(e) => pretty_print::error(&e)?,
+                }
+                // Times that passed while the job was running are skipped.
+                schedule.reschedule(Local::now())?;
+            }
+        }
+    }
+}
+
+fn stop(signal: ShutdownSignal) -> Result<()> {
+    pretty_print::status("Stopping", &format!("received {signal}"), false)?;
+    Err(InterruptedError { signal })
+}
+
+/// A signal that asks csjr to shut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownSignal {
+    /// SIGINT, which is what Ctrl-C sends.
+    Interrupt,
+    /// SIGTERM, which is what service managers and container runtimes send.
+    Terminate,
+}
+
+impl Display for ShutdownSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownSignal::Interrupt => write!(f, "SIGINT"),
+            ShutdownSignal::Terminate => write!(f, "SIGTERM"),
+        }
+    }
+}
+
+/// When a job with a `schedule` runs next.
+#[derive(Debug)]
+struct Schedule<'c> {
+    job_name: &'c str,
+    schedule: &'c str,
+    cron: Cron,
+    next_run: DateTime<Local>,
+}
+
+impl<'c> Schedule<'c> {
+    /// Parses `schedule`, the cron expression of the job named `job_name`,
+    /// and finds the first time it matches after `now`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [InvalidScheduleError] if `schedule` isn't a valid cron
+    /// expression, or never matches.
+    fn new(job_name: &'c str, schedule: &'c str, now: DateTime<Local>) -> Result<Self> {
+        let cron = Cron::new(schedule)
+            .parse()
+            .map_err(|err| InvalidScheduleError {
+                job_name: job_name.into(),
+                schedule: schedule.into(),
+                source: Box::new(err),
+            })?;
+        let mut parsed = Self {
+            job_name,
+            schedule,
+            cron,
+            next_run: now,
+        };
+        parsed.reschedule(now)?;
+        Ok(parsed)
+    }
+
+    /// Returns true if the job should have run by `now`.
+    fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.next_run <= now
+    }
+
+    /// Moves the next run to the first time after `now` that the schedule
+    /// matches.
+    fn reschedule(&mut self, now: DateTime<Local>) -> Result<()> {
+        self.next_run =
+            self.cron
+                .find_next_occurrence(&now, false)
+                .map_err(|err| InvalidScheduleError {
+                    job_name: self.job_name.into(),
+                    schedule: self.schedule.into(),
+                    source: Box::new(err),
+                })?;
+        Ok(())
+    }
+}
+
+/// Returns the [Schedule] of each of `jobs` that has one, as of `now`,
+/// sorted by job name.
+fn parse_schedules(jobs: &HashMap<String, Job>, now: DateTime<Local>) -> Result<Vec<Schedule<'_>>> {
+    let mut job_names: Vec<&String> = jobs.keys().collect();
+    job_names.sort();
+    job_names
+        .into_iter()
+        .filter_map(|job_name| {
+            let schedule = jobs[job_name].schedule.as_deref()?;
+            Some(Schedule::new(job_name, schedule, now))
+        })
+        .collect()
+}
+
+/// Returns a future that resolves to the first SIGTERM or Ctrl-C that csjr
+/// receives.
+#[cfg(unix)]
+fn shutdown_signal() -> io::Result<impl std::future::Future<Output = ShutdownSignal>> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    Ok(async move {
+        tokio::select! {
+            _ = sigterm.recv() => ShutdownSignal::Terminate,
+            _ = tokio::signal::ctrl_c() => ShutdownSignal::Interrupt,
+        }
+    })
+}
+
+/// Returns a future that resolves once csjr receives a Ctrl-C.
+#[cfg(not(unix))]
+fn shutdown_signal() -> io::Result<impl std::future::Future<Output = ShutdownSignal>> {
+    Ok(async {
+        let _ = tokio::signal::ctrl_c().await;
+        ShutdownSignal::Interrupt
+    })
+}
+
+#[cfg(test)]
+mod schedule_tests {
+    use super::{parse_schedules, Schedule};
+    use crate::{CloudServiceProvider, Job, JobRunnerError, NotifyHooks};
+    use chrono::{DateTime, Local, TimeZone};
+    use std::collections::HashMap;
+
+    fn at(hour: u32, min: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2022, 4, 18, hour, min, 0).unwrap()
+    }
+
+    fn job(schedule: Option<&str>) -> Job {
+        Job {
+            cloud_service_provider: CloudServiceProvider::GCP,
+            bucket_name: "foo".into(),
+            path_to_remote_inputs: "in".into(),
+            path_to_local_inputs: "in".into(),
+            path_to_local_outputs: "out".into(),
+            path_to_remote_outputs: "out".into(),
+            steps: Vec::new(),
+            schedule: schedule.map(Into::into),
+            atomic_outputs: false,
+            notify: NotifyHooks::default(),
+        }
+    }
+
+    #[test]
+    fn only_jobs_with_schedules_are_scheduled() {
+        let jobs = HashMap::from([
+            ("nightly".to_string(), job(Some("0 2 * * *"))),
+            ("hourly".to_string(), job(Some("0 * * * *"))),
+            ("manual".to_string(), job(None)),
+        ]);
+
+        let schedules = parse_schedules(&jobs, at(0, 30)).unwrap();
+
+        let names: Vec<&str> = schedules.iter().map(|s| s.job_name).collect();
+        assert_eq!(names, vec!["hourly", "nightly"]);
+        assert_eq!(schedules[0].next_run, at(1, 0));
+        assert_eq!(schedules[1].next_run, at(2, 0));
+    }
+
+    #[test]
+    fn invalid_schedule_is_error() {
+        let jobs = HashMap::from([("nightly".to_string(), job(Some("not cron")))]);
+
+        assert!(matches!(
+            parse_schedules(&jobs, at(0, 30)),
+            Err(JobRunnerError::InvalidScheduleError { .. })
+        ));
+    }
+
+    #[test]
+    fn next_run_is_strictly_after_now() {
+        let schedule = Schedule::new("nightly", "0 2 * * *", at(2, 0)).unwrap();
+
+        assert_eq!(
+            schedule.next_run,
+            Local.with_ymd_and_hms(2022, 4, 19, 2, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn job_is_due_once_next_run_has_passed() {
+        let schedule = Schedule::new("hourly", "0 * * * *", at(2, 30)).unwrap();
+
+        assert!(!schedule.is_due(at(2, 59)));
+        assert!(schedule.is_due(at(3, 0)));
+        assert!(schedule.is_due(at(3, 1)));
+    }
+
+    #[test]
+    fn times_missed_while_still_running_are_skipped() {
+        let mut schedule = Schedule::new("hourly", "0 * * * *", at(2, 30)).unwrap();
+
+        // The 3:00 run takes until 5:30, so 4:00 and 5:00 are skipped.
+        schedule.reschedule(at(5, 30)).unwrap();
+
+        assert_eq!(schedule.next_run, at(6, 0));
+        assert!(!schedule.is_due(at(5, 59)));
+    }
+}
@@ -1,22 +1,64 @@
-use crate::{CloudServiceProvider, PathKeyInConfig};
-use std::{error::Error, fmt::Display, io};
+use crate::{CloudServiceProvider, PathKeyInConfig, ShutdownSignal};
+use std::{error::Error, fmt::Display, io, path::PathBuf};
 
 /// JobRunnerError enumerates all possible errors returned by this library.
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum JobRunnerError {
+    /// Represents when a config file can't be opened or read.
+    ConfigReadError { path: PathBuf, source: io::Error },
+
+    /// Represents when a config file's contents aren't valid YAML, or don't
+    /// describe a valid config. `line` and `column` are where in the file the
+    /// problem is (both starting at 1), if that's known.
+    ConfigParseError {
+        path: PathBuf,
+        line: Option<usize>,
+        column: Option<usize>,
+        source: Box<dyn Error>,
+    },
+
+    /// Represents when no config file was passed, and there isn't one named
+    /// `csjr.yaml` in `dir` or any of its parents, or at `user_config` (if
+    /// there's a directory for user config at all).
+    ConfigNotFoundError {
+        dir: PathBuf,
+        user_config: Option<PathBuf>,
+    },
+
+    /// Represents when `csjr init` is asked to write a config file where
+    /// there's already a file.
+    ConfigExistsError { path: PathBuf },
+
     /// Represents when a job is referenced by name, but that job doesn't exist
-    /// in the provided config file.
-    JobNotFoundError { job_name: String },
+    /// in the provided config file. `close_matches` are the names of jobs
+    /// that do exist, and that were maybe meant instead.
+    JobNotFoundError {
+        job_name: String,
+        close_matches: Vec<String>,
+    },
 
     /// Represents when the credentials to authenticate with a storage bucket in
     /// the cloud can't be found.
     BucketCredentialsNotFoundError(CloudServiceProvider),
 
-    /// Represents when a Path can't be stringified (typically because it
-    /// contains non-UTF-8 characters, which is perfectly valid in some
-    /// operating systems).
+    /// Represents when a remote path in a config file can't be used as part
+    /// of an object's name, because it isn't valid UTF-8.
     InvalidPathError(PathKeyInConfig),
 
+    /// Represents when a local file can't be uploaded, because its path isn't
+    /// valid UTF-8 (which is perfectly valid in some operating systems), but
+    /// object names have to be.
+    NonUnicodeFileNameError { path: PathBuf },
+
+    /// Represents when an object can't be saved on disk, because it isn't
+    /// inside the prefix it was downloaded from, or its name has a `..`
+    /// component that would put it somewhere else on disk.
+    InvalidObjectNameError {
+        object_name: String,
+        prefix: PathBuf,
+    },
+
     /// Represents when attempting to list the files/object in a bucket in the
     /// cloud fails.
     ListFilesInBucketError { source: Box<dyn Error> },
@@ -29,6 +71,14 @@ pub enum JobRunnerError {
     /// fails.
     UploadToBucketError { source: Box<dyn Error> },
 
+    /// Represents when attempting to copy an object to somewhere else in the
+    /// same bucket in the cloud fails.
+    CopyWithinBucketError { source: Box<dyn Error> },
+
+    /// Represents when attempting to delete an object from a bucket in the
+    /// cloud fails.
+    DeleteFromBucketError { source: Box<dyn Error> },
+
     /// Represents when a step written in a config file can't be run, most
     /// likely for syntax reasons.
     InvalidStepError { step: String },
@@ -37,21 +87,185 @@ pub enum JobRunnerError {
     /// is `None`, that means the step was terminated by a signal.
     StepNonZeroStatusCodeError { step: String, code: Option<i32> },
 
+    /// Represents when the steps that were asked to be run (numbered starting
+    /// at 1) aren't a range of a job's steps.
+    InvalidStepRangeError {
+        from_step: usize,
+        to_step: usize,
+        num_steps: usize,
+    },
+
+    /// Represents when a job's `schedule` in a config file isn't a valid cron
+    /// expression.
+    InvalidScheduleError {
+        job_name: String,
+        schedule: String,
+        source: Box<dyn Error>,
+    },
+
+    /// Represents when a job is stopped partway through because csjr was asked
+    /// to shut down by `signal`.
+    InterruptedError { signal: ShutdownSignal },
+
+    /// Represents when a message from a notification source isn't a
+    /// notification that csjr understands.
+    InvalidNotificationError {
+        body: String,
+        source: Box<dyn Error>,
+    },
+
+    /// Represents when a job's `path_to_remote_outputs` includes the current
+    /// git commit, but that commit can't be determined.
+    GitCommitNotFoundError { source: Box<dyn Error> },
+
+    /// Represents when there's no checkpoint for the run that was asked to be
+    /// resumed, either because it never started, or because it succeeded.
+    RunNotFoundError { run_id: String },
+
+    /// Represents when the ID of the run that was asked to be resumed isn't
+    /// one that csjr could have made, like one with a path separator in it.
+    InvalidRunIdError { run_id: String },
+
+    /// Represents when a file that csjr uses to keep track of its own state
+    /// between runs can't be read, parsed, or written.
+    StateFileError {
+        path: PathBuf,
+        source: Box<dyn Error>,
+    },
+
+    /// Represents when a report of how jobs went can't be written.
+    ReportError {
+        path: PathBuf,
+        source: Box<dyn Error>,
+    },
+
+    /// Represents when a job's `notify` webhook can't be sent, or its
+    /// `on_success` or `on_failure` command fails.
+    NotifyError { source: Box<dyn Error> },
+
     /// Represents all other cases of [io::Error].
     IOError(io::Error),
 }
 
+/// Exit codes that csjr exits with, depending on what went wrong. Where they
+/// line up, these are the same as the ones in BSD's `sysexits.h`.
+pub mod exit_code {
+    /// Something went wrong that doesn't fit any of the other codes.
+    pub const OTHER: i32 = 1;
+    /// A notification couldn't be parsed, or an object's name (or a file's
+    /// path) can't be mapped between the bucket and the disk.
+    pub const DATA: i32 = 65;
+    /// A step was terminated by a signal, so it doesn't have an exit code of
+    /// its own.
+    pub const STEP_TERMINATED: i32 = 70;
+    /// Reading or writing a file on disk failed.
+    pub const IO: i32 = 74;
+    /// Talking to a bucket failed. Trying again later might work.
+    pub const TRANSFER: i32 = 75;
+    /// Credentials for a bucket couldn't be found.
+    pub const CREDENTIALS: i32 = 77;
+    /// The config file can't be read, or something in it (or on the command
+    /// line) is invalid.
+    pub const CONFIG: i32 = 78;
+    /// csjr was interrupted (with Ctrl-C) before it was done, like
+    /// `128 + SIGINT`.
+    pub const INTERRUPTED: i32 = 130;
+    /// csjr was asked to shut down (with SIGTERM) before it was done, like
+    /// `128 + SIGTERM`.
+    pub const TERMINATED: i32 = 143;
+}
+
+impl JobRunnerError {
+    /// Returns the code that csjr should exit with because of this error. If a
+    /// step exited with a non-zero status code, that's the step's own code.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::ConfigReadError { .. }
+            | Self::ConfigParseError { .. }
+            | Self::ConfigNotFoundError { .. }
+            | Self::ConfigExistsError { .. }
+            | Self::JobNotFoundError { .. }
+            | Self::InvalidPathError(_)
+            | Self::InvalidStepError { .. }
+            | Self::InvalidStepRangeError { .. }
+            | Self::RunNotFoundError { .. }
+            | Self::InvalidRunIdError { .. }
+            | Self::InvalidScheduleError { .. }
+            | Self::GitCommitNotFoundError { .. } => exit_code::CONFIG,
+            Self::BucketCredentialsNotFoundError(_) => exit_code::CREDENTIALS,
+            Self::ListFilesInBucketError { .. }
+            | Self::DownloadFromBucketError { .. }
+            | Self::UploadToBucketError { .. }
+            | Self::CopyWithinBucketError { .. }
+            | Self::DeleteFromBucketError { .. } => exit_code::TRANSFER,
+            Self::StepNonZeroStatusCodeError { code, .. } => {
+                code.unwrap_or(exit_code::STEP_TERMINATED)
+            }
+            Self::InterruptedError {
+                signal: ShutdownSignal::Interrupt,
+            } => exit_code::INTERRUPTED,
+            Self::InterruptedError {
+                signal: ShutdownSignal::Terminate,
+            } => exit_code::TERMINATED,
+            Self::InvalidNotificationError { .. }
+            | Self::NonUnicodeFileNameError { .. }
+            | Self::InvalidObjectNameError { .. } => exit_code::DATA,
+            // Never fails a run (notifications that can't be sent are only
+            // reported), so it never decides what csjr exits with.
+            Self::NotifyError { .. } => exit_code::OTHER,
+            Self::StateFileError { .. } | Self::ReportError { .. } | Self::IOError(_) => {
+                exit_code::IO
+            }
+        }
+    }
+}
+
 impl Error for JobRunnerError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
         match self {
-            Self::JobNotFoundError { job_name: _ } => None,
+            Self::ConfigReadError { path: _, source } => Some(source),
+            Self::ConfigParseError { source, .. } => Some(source.as_ref()),
+            Self::ConfigNotFoundError {
+                dir: _,
+                user_config: _,
+            } => None,
+            Self::ConfigExistsError { path: _ } => None,
+            Self::JobNotFoundError {
+                job_name: _,
+                close_matches: _,
+            } => None,
             Self::BucketCredentialsNotFoundError(_) => None,
             Self::InvalidPathError(_) => None,
+            Self::NonUnicodeFileNameError { path: _ } => None,
+            Self::InvalidObjectNameError {
+                object_name: _,
+                prefix: _,
+            } => None,
             Self::ListFilesInBucketError { source } => Some(source.as_ref()),
             Self::DownloadFromBucketError { source } => Some(source.as_ref()),
             Self::UploadToBucketError { source } => Some(source.as_ref()),
+            Self::CopyWithinBucketError { source } => Some(source.as_ref()),
+            Self::DeleteFromBucketError { source } => Some(source.as_ref()),
             Self::InvalidStepError { step: _ } => None,
             Self::StepNonZeroStatusCodeError { step: _, code: _ } => None,
+            Self::InvalidStepRangeError {
+                from_step: _,
+                to_step: _,
+                num_steps: _,
+            } => None,
+            Self::InvalidScheduleError {
+                job_name: _,
+                schedule: _,
+                source,
+            } => Some(source.as_ref()),
+            Self::InterruptedError { signal: _ } => None,
+            Self::InvalidNotificationError { body: _, source } => Some(source.as_ref()),
+            Self::GitCommitNotFoundError { source } => Some(source.as_ref()),
+            Self::RunNotFoundError { run_id: _ } => None,
+            Self::InvalidRunIdError { run_id: _ } => None,
+            Self::StateFileError { path: _, source } => Some(source.as_ref()),
+            Self::ReportError { path: _, source } => Some(source.as_ref()),
+            Self::NotifyError { source } => Some(source.as_ref()),
             Self::IOError(_) => None,
         }
     }
@@ -60,8 +274,61 @@ impl Error for JobRunnerError {
 impl Display for JobRunnerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::JobNotFoundError { job_name } => {
-                write!(f, "\"{}\" not found in the config file", job_name)
+            Self::ConfigReadError { path, source } => {
+                write!(
+                    f,
+                    "Failed to read config file \"{}\": {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::ConfigParseError { path, source, .. } => {
+                write!(
+                    f,
+                    "Config file \"{}\" is invalid: {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::ConfigNotFoundError { dir, user_config } => {
+                write!(
+                    f,
+                    "Couldn't find a config file. Looked for \"csjr.yaml\" in \"{}\" and its parents",
+                    dir.display()
+                )?;
+                if let Some(user_config) = user_config {
+                    write!(f, ", and for \"{}\"", user_config.display())?;
+                }
+                write!(
+                    f,
+                    ". Pass one with --config, set CSJR_CONFIG, or write one with `csjr init`"
+                )
+            }
+            Self::ConfigExistsError { path } => {
+                write!(
+                    f,
+                    "Not writing a config to \"{}\", because there's already a file there. Pass --force to overwrite it",
+                    path.display()
+                )
+            }
+            Self::JobNotFoundError {
+                job_name,
+                close_matches,
+            } => {
+                write!(f, "\"{}\" not found in the config file", job_name)?;
+                match close_matches.as_slice() {
+                    [] => Ok(()),
+                    [close_match] => write!(f, ". Did you mean \"{}\"?", close_match),
+                    close_matches => write!(
+                        f,
+                        ". Did you mean one of {}?",
+                        close_matches
+                            .iter()
+                            .map(|close_match| format!("\"{close_match}\""))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                }
             }
             Self::BucketCredentialsNotFoundError(cloud_service_provider) => {
                 match cloud_service_provider {
@@ -83,10 +350,30 @@ impl Display for JobRunnerError {
             Self::InvalidPathError(path_key) => {
                 write!(
                     f,
-                    "Value for \"{}\" in config file can't be stringified",
+                    "Value for \"{}\" in config file isn't valid UTF-8, which \
+                    object names in a bucket have to be",
                     path_key
                 )
             }
+            Self::NonUnicodeFileNameError { path } => {
+                write!(
+                    f,
+                    "Can't upload {:?}, because its path isn't valid UTF-8, which \
+                    object names in a bucket have to be",
+                    path
+                )
+            }
+            Self::InvalidObjectNameError {
+                object_name,
+                prefix,
+            } => {
+                write!(
+                    f,
+                    "Can't download \"{}\", because it isn't inside \"{}\"",
+                    object_name,
+                    prefix.display()
+                )
+            }
             Self::ListFilesInBucketError { source } => {
                 write!(
                     f,
@@ -100,6 +387,12 @@ impl Display for JobRunnerError {
             Self::UploadToBucketError { source } => {
                 write!(f, "Failed to upload object to bucket: {}", source)
             }
+            Self::CopyWithinBucketError { source } => {
+                write!(f, "Failed to copy object within bucket: {}", source)
+            }
+            Self::DeleteFromBucketError { source } => {
+                write!(f, "Failed to delete object from bucket: {}", source)
+            }
             Self::InvalidStepError { step } => {
                 // TODO: Improve error message. Can we explain what about the
                 // step is invalid?
@@ -113,6 +406,66 @@ impl Display for JobRunnerError {
                 ),
                 None => write!(f, "\"{}\" was terminated by a signal", step),
             },
+            Self::InvalidStepRangeError {
+                from_step,
+                to_step,
+                num_steps,
+            } => {
+                write!(
+                    f,
+                    "Can't run steps {} through {}, because the job has {} step(s), numbered starting at 1",
+                    from_step, to_step, num_steps
+                )
+            }
+            Self::InvalidScheduleError {
+                job_name,
+                schedule,
+                source,
+            } => {
+                write!(
+                    f,
+                    "Schedule for \"{}\" in config file, \"{}\", is invalid: {}",
+                    job_name, schedule, source
+                )
+            }
+            Self::InterruptedError { signal } => {
+                write!(f, "Stopped early because csjr received {signal}")
+            }
+            Self::InvalidNotificationError { body, source } => {
+                write!(f, "Invalid notification ({}): {}", source, body)
+            }
+            Self::GitCommitNotFoundError { source } => {
+                write!(f, "Failed to determine the current git commit: {}", source)
+            }
+            Self::RunNotFoundError { run_id } => {
+                write!(
+                    f,
+                    "There's nothing to resume for run \"{}\". It either never started, or it already succeeded",
+                    run_id
+                )
+            }
+            Self::InvalidRunIdError { run_id } => {
+                write!(f, "\"{}\" isn't a valid run ID", run_id)
+            }
+            Self::StateFileError { path, source } => {
+                write!(
+                    f,
+                    "Failed to use state file \"{}\": {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::ReportError { path, source } => {
+                write!(
+                    f,
+                    "Failed to write report \"{}\": {}",
+                    path.display(),
+                    source
+                )
+            }
+            Self::NotifyError { source } => {
+                write!(f, "Failed to notify about how a job went: {}", source)
+            }
             Self::IOError(err) => err.fmt(f),
         }
     }
@@ -123,3 +476,73 @@ impl From<io::Error> for JobRunnerError {
         JobRunnerError::IOError(err)
     }
 }
+
+#[cfg(test)]
+mod exit_code_tests {
+    use super::{exit_code, JobRunnerError};
+    use crate::ShutdownSignal;
+
+    #[test]
+    fn ctrl_c_and_sigterm_are_told_apart() {
+        let err = JobRunnerError::InterruptedError {
+            signal: ShutdownSignal::Interrupt,
+        };
+        assert_eq!(err.exit_code(), 130);
+        let err = JobRunnerError::InterruptedError {
+            signal: ShutdownSignal::Terminate,
+        };
+        assert_eq!(err.exit_code(), 143);
+        assert_eq!(exit_code::TERMINATED, 143);
+    }
+
+    #[test]
+    fn step_failure_propagates_step_exit_code() {
+        let err = JobRunnerError::StepNonZeroStatusCodeError {
+            step: "false".into(),
+            code: Some(3),
+        };
+        assert_eq!(err.exit_code(), 3);
+        let err = JobRunnerError::StepNonZeroStatusCodeError {
+            step: "sleep 100".into(),
+            code: None,
+        };
+        assert_eq!(err.exit_code(), exit_code::STEP_TERMINATED);
+    }
+
+    #[test]
+    fn transfer_failure_is_retryable() {
+        let err = JobRunnerError::DownloadFromBucketError {
+            source: "connection reset".into(),
+        };
+        assert_eq!(err.exit_code(), exit_code::TRANSFER);
+    }
+}
+
+#[cfg(test)]
+mod display_tests {
+    use super::JobRunnerError;
+
+    #[test]
+    fn job_not_found_suggests_close_match() {
+        let err = JobRunnerError::JobNotFoundError {
+            job_name: "resize_imgaes".into(),
+            close_matches: vec!["resize_images".into()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "\"resize_imgaes\" not found in the config file. Did you mean \"resize_images\"?"
+        );
+    }
+
+    #[test]
+    fn job_not_found_suggests_several_close_matches() {
+        let err = JobRunnerError::JobNotFoundError {
+            job_name: "resize".into(),
+            close_matches: vec!["resize_images".into(), "resize_videos".into()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "\"resize\" not found in the config file. Did you mean one of \"resize_images\", \"resize_videos\"?"
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    errors::JobRunnerError::{NotifyError, StepNonZeroStatusCodeError},
+    pretty_print,
+    report::{ErrorReport, JobReport, JobStatus},
+    step_runner::{shell, Step},
+    Result,
+};
+use indicatif::HumanDuration;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How long to wait for a webhook to respond before giving up on it.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What to do once a job finishes, to let people (or other programs) know how
+/// it went.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct NotifyHooks {
+    /// URL that a summary of each run is POSTed to, as JSON. The summary has a
+    /// `text` field, so it can be a Slack (or Slack-compatible) incoming
+    /// webhook.
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// Command that's run after the job succeeds.
+    #[serde(default)]
+    pub on_success: Option<String>,
+    /// Command that's run after the job fails.
+    #[serde(default)]
+    pub on_failure: Option<String>,
+}
+
+/// The JSON body that's POSTed to a webhook.
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    /// A one-line summary, for chat apps to display.
+    text: String,
+    job: &'a str,
+    status: JobStatus,
+    #[serde(
+        rename = "duration_ms",
+        serialize_with = "pretty_print::serialize_as_millis"
+    )]
+    duration: Duration,
+    error: Option<&'a ErrorReport>,
+    output_prefix: Option<&'a str>,
+}
+
+impl NotifyHooks {
+    /// Sends the run described by `report` to the webhook, and runs either
+    /// the `on_success` or `on_failure` command. Runs that were skipped
+    /// because there was nothing new to process don't trigger anything.
+    ///
+    /// The command runs even if the webhook can't be reached. If either one
+    /// fails, the first error is returned.
+    pub async fn run(&self, report: &JobReport) -> Result<()> {
+        let command = match report.status {
+            JobStatus::Succeeded => &self.on_success,
+            JobStatus::Failed => &self.on_failure,
+            JobStatus::Skipped => return Ok(()),
+        };
+        let webhook_result = match &self.webhook {
+            Some(url) => send_webhook(url, report).await,
+            None => Ok(()),
+        };
+        let command_result = match command {
+            Some(command) => run_command(command, report),
+            None => Ok(()),
+        };
+        webhook_result.and(command_result)
+    }
+}
+
+async fn send_webhook(url: &str, report: &JobReport) -> Result<()> {
+    let payload = Payload {
+        text: summarize(report),
+        job: &report.job,
+        status: report.status,
+        duration: report.duration,
+        error: report.error.as_ref(),
+        output_prefix: report.stats.remote_outputs.as_deref(),
+    };
+    reqwest::Client::new()
+        .post(url)
+        .timeout(WEBHOOK_TIMEOUT)
+        .json(&payload)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| NotifyError {
+            source: Box::new(err),
+        })?;
+    Ok(())
+}
+
+/// Runs `command` the same way that steps are run, with details about the
+/// run described by `report` in `CSJR_*` environment variables.
+fn run_command(command: &str, report: &JobReport) -> Result<()> {
+    let status = shell::command(&Step::from(command))?
+        .env("CSJR_JOB", &report.job)
+        .env("CSJR_STATUS", report.status.as_str())
+        .env("CSJR_DURATION_MS", report.duration.as_millis().to_string())
+        .env(
+            "CSJR_ERROR",
+            report.error.as_ref().map_or("", |e| e.message.as_str()),
+        )
+        .env(
+            "CSJR_OUTPUT_PREFIX",
+            report.stats.remote_outputs.as_deref().unwrap_or_default(),
+        )
+        .status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(NotifyError {
+            source: Box::new(StepNonZeroStatusCodeError {
+                step: command.into(),
+                code: status.code(),
+            }),
+        })
+    }
+}
+
+/// Returns a one-line summary of the run described by `report`.
+fn summarize(report: &JobReport) -> String {
+    let duration = HumanDuration(report.duration);
+    match &report.error {
+        Some(error) => format!(
+            "csjr: {} failed after {duration}: {}",
+            report.job, error.message
+        ),
+        None => format!(
+            "csjr: {} {} in {duration}",
+            report.job,
+            report.status.as_str()
+        ),
+    }
+}
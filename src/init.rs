@@ -0,0 +1,277 @@
+use crate::{
+    bucket::Bucket,
+    errors::JobRunnerError::{ConfigExistsError, InvalidPathError},
+    pretty_print, CloudServiceProvider, Config, PathKeyInConfig, Result,
+};
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+/// The values that `csjr init` fills a starter config in with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scaffold {
+    pub job_name: String,
+    pub cloud_service_provider: String,
+    pub bucket_name: String,
+    pub path_to_remote_inputs: String,
+    pub path_to_local_inputs: String,
+    pub path_to_local_outputs: String,
+    pub path_to_remote_outputs: String,
+}
+
+impl Default for Scaffold {
+    fn default() -> Self {
+        Self {
+            job_name: "your_job_name_here".into(),
+            cloud_service_provider: "GCP".into(),
+            bucket_name: "your_bucket_name".into(),
+            path_to_remote_inputs: "path/to/some/dir/in/your/bucket".into(),
+            path_to_local_inputs: "path/to/save/inputs/on/disk".into(),
+            path_to_local_outputs: "path/to/save/outputs/on/disk".into(),
+            path_to_remote_outputs: "path/to/some/other/dir/in/your/bucket".into(),
+        }
+    }
+}
+
+impl Scaffold {
+    /// Asks for each of the values in a starter config on `output`, and reads
+    /// the answers from `input`. Leaving an answer blank (or running out of
+    /// input) keeps the value that's shown in brackets.
+    pub fn prompt(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<Self> {
+        let defaults = Self::default();
+        let job_name = ask(input, output, "Job name", &defaults.job_name)?;
+        let cloud_service_provider = loop {
+            let answer = ask(
+                input,
+                output,
+                "Cloud service provider",
+                &defaults.cloud_service_provider,
+            )?;
+            match serde_yaml::from_str::<CloudServiceProvider>(&answer) {
+                Ok(_) => break answer,
+                Err(_) => writeln!(output, "Only GCP is supported so far")?,
+            }
+        };
+        let bucket_name = ask(input, output, "Bucket name", &defaults.bucket_name)?;
+        let path_to_remote_inputs = ask(
+            input,
+            output,
+            "Directory in the bucket to download inputs from",
+            &defaults.path_to_remote_inputs,
+        )?;
+        let path_to_local_inputs = ask(
+            input,
+            output,
+            "Directory on disk to save inputs to",
+            &format!("inputs/{job_name}"),
+        )?;
+        let path_to_local_outputs = ask(
+            input,
+            output,
+            "Directory on disk that the job's steps write outputs to",
+            &format!("outputs/{job_name}"),
+        )?;
+        let path_to_remote_outputs = ask(
+            input,
+            output,
+            "Directory in the bucket to upload outputs to",
+            &defaults.path_to_remote_outputs,
+        )?;
+        Ok(Self {
+            job_name,
+            cloud_service_provider,
+            bucket_name,
+            path_to_remote_inputs,
+            path_to_local_inputs,
+            path_to_local_outputs,
+            path_to_remote_outputs,
+        })
+    }
+
+    /// Returns a starter config with a single job that's filled in with these
+    /// values, with comments explaining what each key is for.
+    pub fn render(&self) -> String {
+        format!(
+            r#"# Config for csjr. Each job downloads the objects in a directory in a bucket,
+# runs some steps on them, and uploads whatever the steps write back to the
+# bucket. Run a job with `csjr -c <this file> <job name>`.
+jobs:
+  {}:
+    # Where the job's bucket is hosted. GCP is the only one supported so far.
+    cloud_service_provider: {}
+    bucket_name: {}
+    # Directory in the bucket to download inputs from, and where on disk to
+    # save them.
+    path_to_remote_inputs: {}
+    path_to_local_inputs: {}
+    # Where on disk the job's steps write their outputs, and the directory in
+    # the bucket to upload them to. The remote directory can include
+    # [timestamp], [run_id], or [git_sha], to keep each run's outputs separate.
+    path_to_local_outputs: {}
+    path_to_remote_outputs: {}
+    # Optional. Publish outputs only once they've all been uploaded, along with
+    # a _SUCCESS marker object.
+    atomic_outputs: false
+    # Optional. When `csjr daemon` should run this job, as a cron expression.
+    # schedule: "0 2 * * *"
+    # Optional. How to let people know how this job went.
+    # notify:
+    #   webhook: "https://hooks.slack.com/services/your/webhook/url"
+    #   on_success: "sh notify.sh"
+    #   on_failure: "sh page_someone.sh"
+    # Commands to run, in order. Tags like [path_to_local_inputs] are replaced
+    # with the paths above.
+    steps:
+      - "find [path_to_local_inputs] -type f"
+      - "cp -r [path_to_local_inputs] [path_to_local_outputs]"
+"#,
+            quoted(&self.job_name),
+            self.cloud_service_provider,
+            quoted(&self.bucket_name),
+            quoted(&self.path_to_remote_inputs),
+            quoted(&self.path_to_local_inputs),
+            quoted(&self.path_to_local_outputs),
+            quoted(&self.path_to_remote_outputs),
+        )
+    }
+}
+
+/// Writes `contents` to a new config file at `path`, creating any missing
+/// parent directories.
+///
+/// # Errors
+///
+/// Returns a [ConfigExistsError] if there's already a file at `path`, unless
+/// `force` is set.
+pub fn write_config(path: &Path, contents: &str, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        return Err(ConfigExistsError { path: path.into() });
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, contents)?;
+    pretty_print::status("Wrote", &format!("\"{}\"", path.display()), false)?;
+    Ok(())
+}
+
+/// Checks that the job named `job_name` in the config file at `path` can
+/// list the objects in its `path_to_remote_inputs` directory, and prints how
+/// many there are.
+pub async fn check_access(path: &Path, job_name: &str) -> Result<()> {
+    let config = Config::from_path(path)?;
+    let job = config.job(job_name)?;
+    let prefix = job
+        .path_to_remote_inputs
+        .to_str()
+        .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+    let objects = job.bucket()?.list_objects(prefix).await?;
+    pretty_print::status(
+        "Found",
+        &format!(
+            "{} object(s) in \"{}\" in bucket \"{}\"",
+            objects.len(),
+            job.path_to_remote_inputs.display(),
+            job.bucket_name
+        ),
+        false,
+    )?;
+    Ok(())
+}
+
+/// Writes `question` to `output`, and returns the answer read from `input`,
+/// or `default` if the answer is blank.
+fn ask(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+) -> io::Result<String> {
+    write!(output, "{question} [{default}]: ")?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() {
+        default.into()
+    } else {
+        answer.into()
+    })
+}
+
+/// Returns `s` as a double-quoted YAML string. JSON strings happen to be valid
+/// YAML, and escape everything that needs to be.
+fn quoted(s: &str) -> String {
+    serde_json::to_string(s).expect("Serializing a string as JSON can't fail")
+}
+
+#[cfg(test)]
+mod scaffold_tests {
+    use super::Scaffold;
+    use crate::Config;
+
+    #[test]
+    fn default_renders_valid_config() {
+        let config: Config = serde_yaml::from_str(&Scaffold::default().render()).unwrap();
+        let job = &config.jobs["your_job_name_here"];
+        assert_eq!(job.bucket_name, "your_bucket_name");
+        assert_eq!(
+            job.path_to_remote_inputs.to_str(),
+            Some("path/to/some/dir/in/your/bucket")
+        );
+        assert_eq!(job.steps.len(), 2);
+        assert!(job.schedule.is_none());
+    }
+
+    #[test]
+    fn values_that_need_escaping_render_valid_config() {
+        let scaffold = Scaffold {
+            job_name: "resize: \"images\"".into(),
+            path_to_local_inputs: "# not a comment".into(),
+            ..Default::default()
+        };
+        let config: Config = serde_yaml::from_str(&scaffold.render()).unwrap();
+        let job = &config.jobs["resize: \"images\""];
+        assert_eq!(job.path_to_local_inputs.to_str(), Some("# not a comment"));
+    }
+
+    #[test]
+    fn prompt_keeps_defaults_for_blank_answers() {
+        let mut input = "resize\n\nmy-bucket\nimg\n\n\nimg-small\n".as_bytes();
+        let mut output = Vec::new();
+
+        let scaffold = Scaffold::prompt(&mut input, &mut output).unwrap();
+
+        assert_eq!(
+            scaffold,
+            Scaffold {
+                job_name: "resize".into(),
+                cloud_service_provider: "GCP".into(),
+                bucket_name: "my-bucket".into(),
+                path_to_remote_inputs: "img".into(),
+                path_to_local_inputs: "inputs/resize".into(),
+                path_to_local_outputs: "outputs/resize".into(),
+                path_to_remote_outputs: "img-small".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn prompt_asks_again_for_unsupported_provider() {
+        let mut input = "resize\nAzure\ngcp\nGCP\n".as_bytes();
+        let mut output = Vec::new();
+
+        let scaffold = Scaffold::prompt(&mut input, &mut output).unwrap();
+
+        assert_eq!(scaffold.cloud_service_provider, "GCP");
+        assert_eq!(
+            String::from_utf8(output)
+                .unwrap()
+                .matches("Only GCP is supported so far")
+                .count(),
+            2
+        );
+    }
+}
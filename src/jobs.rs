@@ -0,0 +1,180 @@
+use crate::{Config, Job, JobRunner, Result};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+};
+
+/// Headers of the columns that `csjr jobs` lists jobs in.
+const HEADERS: [&str; 6] = [
+    "NAME",
+    "PROVIDER",
+    "BUCKET",
+    "REMOTE INPUTS",
+    "REMOTE OUTPUTS",
+    "STEPS",
+];
+
+impl<'a> JobRunner<'a> {
+    /// Writes a table of every job in the config to stdout, sorted by name,
+    /// with each job's cloud service provider, bucket, remote inputs and
+    /// outputs, and how many steps it has. If `names_only` is set, just writes
+    /// each job's name on a line of its own, which is what shell completions
+    /// read.
+    pub fn jobs(&self, names_only: bool) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        if names_only {
+            write_job_names(&mut stdout, self.config)?;
+        } else {
+            write_jobs(&mut stdout, self.config)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the job named `job_name` to stdout as YAML, the way csjr sees it
+    /// when it runs the job: keys that were left out of the config are filled
+    /// in with their defaults, and the `[path_to_*_*]` tags in its steps are
+    /// substituted. Run identifiers in `path_to_remote_outputs` are left as
+    /// they are, since they're only filled in once a run starts.
+    pub fn show(&self, job_name: &str) -> Result<()> {
+        let job = self.job(job_name)?;
+        write_job(&mut io::stdout().lock(), job_name, job)?;
+        Ok(())
+    }
+}
+
+/// Writes the name of each job in `config` to `w`, one per line, in order.
+fn write_job_names(w: &mut impl Write, config: &Config) -> io::Result<()> {
+    let mut names: Vec<&String> = config.jobs.keys().collect();
+    names.sort();
+    for name in names {
+        writeln!(w, "{name}")?;
+    }
+    Ok(())
+}
+
+/// Writes a table of the jobs in `config` to `w`.
+fn write_jobs(w: &mut impl Write, config: &Config) -> io::Result<()> {
+    let jobs: BTreeMap<&String, &Job> = config.jobs.iter().collect();
+    let rows: Vec<[String; 6]> = jobs
+        .into_iter()
+        .map(|(name, job)| {
+            [
+                name.clone(),
+                job.cloud_service_provider.to_string(),
+                job.bucket_name.clone(),
+                job.path_to_remote_inputs.display().to_string(),
+                job.path_to_remote_outputs.display().to_string(),
+                job.steps.len().to_string(),
+            ]
+        })
+        .collect();
+    let mut widths = HEADERS.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    write_row(w, &HEADERS, &widths)?;
+    for row in &rows {
+        write_row(w, row, &widths)?;
+    }
+    Ok(())
+}
+
+/// Writes one row of the table of jobs to `w`, with each cell padded to its
+/// column's width. The last cell isn't padded.
+fn write_row(w: &mut impl Write, row: &[impl AsRef<str>], widths: &[usize]) -> io::Result<()> {
+    let last = row.len() - 1;
+    for (i, (cell, width)) in row.iter().zip(widths).enumerate() {
+        if i == last {
+            writeln!(w, "{}", cell.as_ref())?;
+        } else {
+            write!(w, "{:<width$}  ", cell.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `job`, named `job_name`, to `w` as YAML, resolved the way
+/// [JobRunner::show] describes.
+fn write_job(w: &mut impl Write, job_name: &str, job: &Job) -> io::Result<()> {
+    let resolved = Job {
+        steps: job.get_steps().iter().map(ToString::to_string).collect(),
+        ..job.clone()
+    };
+    let yaml = serde_yaml::to_string(&BTreeMap::from([(job_name, resolved)]))
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    w.write_all(yaml.trim_start_matches("---\n").as_bytes())
+}
+
+#[cfg(test)]
+mod jobs_tests {
+    use super::{write_job, write_job_names, write_jobs};
+    use crate::{CloudServiceProvider, Config, Job, NotifyHooks};
+    use std::collections::HashMap;
+
+    fn job(bucket_name: &str, num_steps: usize) -> Job {
+        Job {
+            cloud_service_provider: CloudServiceProvider::GCP,
+            bucket_name: bucket_name.into(),
+            path_to_remote_inputs: "img".into(),
+            path_to_local_inputs: "inputs".into(),
+            path_to_local_outputs: "outputs".into(),
+            path_to_remote_outputs: "img-small/[timestamp]".into(),
+            steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into(); num_steps],
+            schedule: None,
+            atomic_outputs: false,
+            notify: NotifyHooks::default(),
+        }
+    }
+
+    #[test]
+    fn table_of_jobs_sorted_by_name() {
+        let config = Config {
+            jobs: HashMap::from([
+                ("thumbnail".into(), job("photos", 2)),
+                ("resize".into(), job("my-very-long-bucket", 1)),
+            ]),
+        };
+        let mut w = Vec::new();
+
+        write_jobs(&mut w, &config).unwrap();
+
+        assert_eq!(
+            String::from_utf8(w).unwrap(),
+            "NAME       PROVIDER  BUCKET               REMOTE INPUTS  REMOTE OUTPUTS         STEPS\n\
+             resize     GCP       my-very-long-bucket  img            img-small/[timestamp]  1\n\
+             thumbnail  GCP       photos               img            img-small/[timestamp]  2\n"
+        );
+    }
+
+    #[test]
+    fn job_names_sorted() {
+        let config = Config {
+            jobs: HashMap::from([
+                ("thumbnail".into(), job("photos", 2)),
+                ("resize".into(), job("photos", 1)),
+            ]),
+        };
+        let mut w = Vec::new();
+
+        write_job_names(&mut w, &config).unwrap();
+
+        assert_eq!(String::from_utf8(w).unwrap(), "resize\nthumbnail\n");
+    }
+
+    #[test]
+    fn job_with_defaults_and_tags_filled_in() {
+        let mut w = Vec::new();
+
+        write_job(&mut w, "resize", &job("photos", 1)).unwrap();
+
+        let shown = String::from_utf8(w).unwrap();
+        assert!(shown.starts_with("resize:\n"));
+        assert!(shown.contains("atomic_outputs: false"));
+        assert!(shown.contains("schedule: ~"));
+        assert!(shown.contains("on_failure: ~"));
+        assert!(shown.contains("- cp -r inputs outputs"));
+        assert!(shown.contains("path_to_remote_outputs: \"img-small/[timestamp]\""));
+    }
+}
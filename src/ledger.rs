@@ -0,0 +1,117 @@
+use crate::{bucket::Object, errors::JobRunnerError::StateFileError, Result};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// A record of which remote objects a job has already processed successfully,
+/// so that rerunning that job doesn't process them again.
+///
+/// Persisted on disk as a JSON-lines file with one [Object] per line. Since
+/// objects are identified by their name _and_ their generation, overwriting an
+/// object in the bucket makes it eligible to be processed again.
+#[derive(Debug, Default)]
+pub struct Ledger {
+    /// Where this ledger is persisted. If `None`, the ledger only lives in
+    /// memory.
+    path: Option<PathBuf>,
+    processed: HashSet<Object>,
+}
+
+impl Ledger {
+    /// Reads the ledger file at `path`. Returns an empty ledger if that file
+    /// doesn't exist yet.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut processed = HashSet::new();
+        if path.exists() {
+            for line in fs::read_to_string(path)?.lines() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let object = serde_json::from_str(line).map_err(|err| StateFileError {
+                    path: path.into(),
+                    source: Box::new(err),
+                })?;
+                processed.insert(object);
+            }
+        }
+        Ok(Self {
+            path: Some(path.into()),
+            processed,
+        })
+    }
+
+    /// Returns true if `object` has already been processed.
+    pub fn contains(&self, object: &Object) -> bool {
+        self.processed.contains(object)
+    }
+
+    /// Records each of the provided `objects` as processed, and appends them
+    /// to the ledger file. Creates the ledger file, and any missing parent
+    /// directories, if they don't exist yet.
+    pub fn record(&mut self, objects: &[Object]) -> Result<()> {
+        let new_objects: Vec<&Object> = objects
+            .iter()
+            .filter(|o| !self.processed.contains(o))
+            .collect();
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            for object in &new_objects {
+                let line = serde_json::to_string(object).map_err(|err| StateFileError {
+                    path: path.clone(),
+                    source: Box::new(err),
+                })?;
+                writeln!(file, "{line}")?;
+            }
+        }
+        self.processed.extend(new_objects.into_iter().cloned());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod ledger_tests {
+    use super::Ledger;
+    use crate::bucket::Object;
+    use std::fs;
+
+    fn object(name: &str, generation: i64) -> Object {
+        Object {
+            name: name.into(),
+            generation,
+        }
+    }
+
+    #[test]
+    fn records_persist_between_opens() {
+        let path = std::env::temp_dir().join("csjr-ledger-tests-persist.jsonl");
+        let _ = fs::remove_file(&path);
+
+        let mut ledger = Ledger::open(&path).unwrap();
+        assert!(!ledger.contains(&object("foo.txt", 1)));
+        ledger
+            .record(&[object("foo.txt", 1), object("bar/baz.txt", 2)])
+            .unwrap();
+        // Recording an object twice shouldn't write it twice.
+        ledger.record(&[object("foo.txt", 1)]).unwrap();
+
+        let ledger = Ledger::open(&path).unwrap();
+        assert!(ledger.contains(&object("foo.txt", 1)));
+        assert!(ledger.contains(&object("bar/baz.txt", 2)));
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn new_generation_is_not_processed() {
+        let mut ledger = Ledger::default();
+        ledger.record(&[object("foo.txt", 1)]).unwrap();
+        assert!(ledger.contains(&object("foo.txt", 1)));
+        assert!(!ledger.contains(&object("foo.txt", 2)));
+    }
+}
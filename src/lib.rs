@@ -1,13 +1,61 @@
-mod bucket;
+pub mod bucket;
+mod checkpoint;
+pub mod completions;
+mod daemon;
 mod errors;
+mod hooks;
+pub mod init;
+mod jobs;
+mod ledger;
+pub mod ls;
+pub mod metrics;
+pub mod notification;
 pub mod pretty_print;
-mod step_runner;
+mod publish;
+pub mod report;
+pub mod step_runner;
+mod suggest;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+mod transfer;
+mod versioning;
+mod watch;
 
-use errors::JobRunnerError::{self, InvalidPathError, JobNotFoundError};
-use serde::Deserialize;
-use std::{collections::HashMap, error::Error, fmt::Display, io, path::PathBuf};
+use bucket::keys;
+pub use checkpoint::Checkpoint;
+use chrono::{DateTime, Utc};
+pub use daemon::ShutdownSignal;
+use errors::JobRunnerError::{
+    ConfigNotFoundError, ConfigParseError, ConfigReadError, InterruptedError, InvalidPathError,
+    InvalidStepRangeError, JobNotFoundError, StepNonZeroStatusCodeError,
+};
+pub use errors::{exit_code, JobRunnerError};
+pub use hooks::NotifyHooks;
+pub use ledger::Ledger;
+use metrics::Metrics;
+use pretty_print::{Event, Verbosity};
+use report::{JobReport, JobStats, Report};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env,
+    error::Error,
+    ffi::{OsStr, OsString},
+    fmt::Display,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::{Duration, Instant},
+};
+use step_runner::Step;
+use tracing::{info_span, instrument, Span};
 
-type Result<T, E = JobRunnerError> = std::result::Result<T, E>;
+pub type Result<T, E = JobRunnerError> = std::result::Result<T, E>;
+
+/// Name of the config file that's looked for in the current directory and its
+/// parents, if no config file is passed explicitly.
+pub const CONFIG_FILE_NAME: &str = "csjr.yaml";
 
 /// Representations of the different keys in a config file whose values are
 /// [PathBuf]s.
@@ -30,12 +78,20 @@ impl Display for PathKeyInConfig {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum CloudServiceProvider {
     GCP,
 }
 
-#[derive(Debug, Deserialize)]
+impl Display for CloudServiceProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CloudServiceProvider::GCP => write!(f, "GCP"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Job {
     pub cloud_service_provider: CloudServiceProvider,
     pub bucket_name: String,
@@ -44,6 +100,18 @@ pub struct Job {
     pub path_to_local_outputs: PathBuf,
     pub path_to_remote_outputs: PathBuf,
     pub steps: Vec<String>,
+    /// Cron expression (e.g. `"0 2 * * *"`) for when `csjr daemon` should run
+    /// this job. The daemon never runs jobs that don't have one.
+    #[serde(default)]
+    pub schedule: Option<String>,
+    /// Upload outputs to a staging prefix first, and only publish them to
+    /// `path_to_remote_outputs` (along with a `_SUCCESS` marker) once they've
+    /// all been uploaded.
+    #[serde(default)]
+    pub atomic_outputs: bool,
+    /// What to do once this job finishes, to let people know how it went.
+    #[serde(default)]
+    pub notify: NotifyHooks,
 }
 
 #[derive(Debug, Deserialize)]
@@ -51,17 +119,158 @@ pub struct Config {
     pub jobs: HashMap<String, Job>,
 }
 
+impl Config {
+    /// Fetches the [Job] with the name `job_name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [JobNotFoundError] if there's no such job, along with the
+    /// names of any jobs that are close to `job_name`, to suggest instead.
+    pub fn job(&self, job_name: &str) -> Result<&Job> {
+        self.jobs.get(job_name).ok_or_else(|| JobNotFoundError {
+            job_name: job_name.into(),
+            close_matches: suggest::close_matches(job_name, self.jobs.keys()),
+        })
+    }
+
+    /// Finds the config file to use when none is passed explicitly: the
+    /// closest `csjr.yaml` in the current directory or any of its parents, or
+    /// failing that, `csjr/config.yaml` in the user's config directory
+    /// (`$XDG_CONFIG_HOME`, or `~/.config` if that isn't set).
+    ///
+    /// # Errors
+    ///
+    /// Returns a [ConfigNotFoundError] if there's no config file in any of
+    /// those places.
+    pub fn find() -> Result<PathBuf> {
+        let user_config_dir = env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        Self::find_from(&env::current_dir()?, user_config_dir.as_deref())
+    }
+
+    /// Like [Config::find], but starts looking for `csjr.yaml` in `dir`
+    /// instead of the current directory, and looks for `csjr/config.yaml` in
+    /// `user_config_dir`, if there is one.
+    pub fn find_from(dir: &Path, user_config_dir: Option<&Path>) -> Result<PathBuf> {
+        if let Some(path) = dir
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+        {
+            return Ok(path);
+        }
+        let user_config = user_config_dir.map(|dir| dir.join("csjr").join("config.yaml"));
+        match user_config {
+            Some(path) if path.is_file() => Ok(path),
+            user_config => Err(ConfigNotFoundError {
+                dir: dir.into(),
+                user_config,
+            }),
+        }
+    }
+
+    /// Reads and parses the YAML config file at `path`.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path).map_err(|err| ConfigReadError {
+            path: path.into(),
+            source: err,
+        })?;
+        serde_yaml::from_str(&contents).map_err(|err| ConfigParseError {
+            path: path.into(),
+            line: err.location().map(|l| l.line()),
+            column: err.location().map(|l| l.column()),
+            source: Box::new(err),
+        })
+    }
+}
+
+/// Knobs that change how jobs are run.
+#[derive(Debug)]
+pub struct RunOptions {
+    /// Directory where each job's [Ledger] is kept.
+    pub path_to_ledgers: PathBuf,
+    /// Directory where the [Checkpoint] of each run that hasn't succeeded
+    /// (yet) is kept, so that it can be resumed.
+    pub path_to_runs: PathBuf,
+    /// Run jobs on objects even if their ledger says they've already been
+    /// processed.
+    pub reprocess: bool,
+    /// Once this is set (to the signal that asked csjr to shut down), jobs
+    /// stop as soon as the step they're currently running finishes.
+    pub stop_requested: Arc<OnceLock<ShutdownSignal>>,
+    /// If set, metrics about every job that's been run are written to this
+    /// file after each run, for node_exporter's textfile collector.
+    pub path_to_metrics_file: Option<PathBuf>,
+    /// Number (starting at 1) of the first step to run. Earlier steps are
+    /// skipped.
+    pub from_step: Option<usize>,
+    /// Number (starting at 1) of the last step to run. Later steps are
+    /// skipped.
+    pub to_step: Option<usize>,
+    /// Don't download anything, and run steps on whatever is already in
+    /// `path_to_local_inputs`.
+    pub skip_download: bool,
+    /// Don't upload anything once steps have run.
+    pub skip_upload: bool,
+}
+
+impl RunOptions {
+    /// Returns true if these options leave out any part of a run. Partial runs
+    /// aren't recorded in ledgers, since their objects weren't processed from
+    /// start to finish, and they aren't checkpointed either.
+    fn is_partial(&self) -> bool {
+        self.from_step.is_some() || self.to_step.is_some() || self.skip_download || self.skip_upload
+    }
+}
+
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            path_to_ledgers: ".csjr/ledger".into(),
+            path_to_runs: ".csjr/runs".into(),
+            reprocess: false,
+            stop_requested: Arc::default(),
+            path_to_metrics_file: None,
+            from_step: None,
+            to_step: None,
+            skip_download: false,
+            skip_upload: false,
+        }
+    }
+}
+
 pub struct JobRunner<'a> {
     config: &'a Config,
+    options: RunOptions,
     /// Counter that keeps track of which job we're currently running.
     job_counter: u8,
+    /// How each job that's been run so far went.
+    job_reports: Vec<JobReport>,
+    metrics: Arc<Metrics>,
 }
 
 impl<'a> JobRunner<'a> {
-    pub fn new(config: &'a Config) -> Self {
+    pub fn new(config: &'a Config, options: RunOptions) -> Self {
         Self {
             config,
+            options,
             job_counter: 0,
+            job_reports: Vec::new(),
+            metrics: Arc::default(),
+        }
+    }
+
+    /// Returns the [Metrics] that every job this runner runs is recorded in.
+    pub fn metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Returns a [Report] of how each job that's been run so far went.
+    pub fn report(&self) -> Report {
+        Report {
+            jobs: self.job_reports.clone(),
         }
     }
 
@@ -75,24 +284,215 @@ impl<'a> JobRunner<'a> {
 
     /// Runs the job with the provided `job_name`.
     ///
-    /// Fetches the [Job] with the name `job_name`, grabs the appropriate
-    /// [bucket::Bucket] and [step_runner::StepRunner] implementations, and
-    /// calls the job's `run()` method.
+    /// Fetches the [Job] with the name `job_name`, prints which job is being
+    /// run, and runs it.
     pub async fn run_one(&mut self, job_name: &str) -> Result<()> {
-        let job = self
-            .config
-            .jobs
-            .get(job_name)
-            .ok_or_else(|| JobNotFoundError {
-                job_name: job_name.into(),
-            })?;
-        let bucket = match job.cloud_service_provider {
-            CloudServiceProvider::GCP => bucket::gcp::CloudStorageBucket::new(&job.bucket_name)?,
+        self.job(job_name)?;
+        self.print_running_job_status_message(job_name)?;
+        self.run_job(job_name).await
+    }
+
+    /// Resumes the run with the ID `run_id`, which was interrupted or failed,
+    /// where it left off. See [Job::run_with_checkpoint].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [JobRunnerError::RunNotFoundError] if there's nothing to
+    /// resume for that run, or a [JobNotFoundError] if its job isn't in the
+    /// config anymore.
+    pub async fn resume(&mut self, run_id: &str) -> Result<()> {
+        let checkpoint = Checkpoint::open(&self.options.path_to_runs, run_id)?;
+        let job_name = checkpoint.job().to_string();
+        self.job(&job_name)?;
+        pretty_print::status("Resuming", &format!("{job_name} ({run_id})..."), false)?;
+        self.run_job_on(&job_name, None, Some(checkpoint)).await
+    }
+
+    /// Fetches the [Job] with the name `job_name`, grabs the appropriate
+    /// [bucket::Bucket] and [step_runner::StepRunner] implementations and
+    /// [Ledger], and calls the job's `run()` method.
+    async fn run_job(&mut self, job_name: &str) -> Result<()> {
+        self.run_job_on(job_name, None, None).await
+    }
+
+    /// Like [JobRunner::run_job_on], but only keeps the run's [Checkpoint] in
+    /// memory. For `daemon` and `listen`, which run jobs over and over, and
+    /// retry a failed run on their own the next time around, so checkpoint
+    /// files for their failed runs would just pile up.
+    async fn run_job_without_checkpoint(
+        &mut self,
+        job_name: &str,
+        objects: Option<&[bucket::Object]>,
+    ) -> Result<()> {
+        self.run_job_on(job_name, objects, Some(Checkpoint::default()))
+            .await
+    }
+
+    /// Runs the job with the name `job_name` on `objects`, or on everything in
+    /// its `path_to_remote_inputs` directory if `objects` is `None`, using
+    /// its own bucket. See [JobRunner::run_job_on_bucket].
+    async fn run_job_on(
+        &mut self,
+        job_name: &str,
+        objects: Option<&[bucket::Object]>,
+        checkpoint: Option<Checkpoint>,
+    ) -> Result<()> {
+        // Fail before connecting to anything if asked to run steps that
+        // don't exist.
+        let bucket = self.job(job_name).and_then(|job| {
+            job.step_range(&self.options)?;
+            job.bucket()
+        });
+        match bucket {
+            Ok(bucket) => {
+                self.run_job_on_bucket(job_name, &bucket, objects, checkpoint)
+                    .await
+            }
+            Err(e) => {
+                pretty_print::event(&Event::JobStarted { job: job_name })?;
+                self.finish_run(
+                    job_name,
+                    Utc::now(),
+                    Duration::ZERO,
+                    JobStats::default(),
+                    Err(e),
+                )
+                .await
+            }
+        }
+    }
+
+    /// Runs the job with the name `job_name` on `objects` in `bucket`, or on
+    /// everything in its `path_to_remote_inputs` directory if `objects` is
+    /// `None`. Logs when the job starts and how it went, and adds it to this
+    /// runner's [Report] and [Metrics]. Finally, runs the job's
+    /// [NotifyHooks].
+    ///
+    /// Picks up where a previous run left off if given its `checkpoint`.
+    /// Otherwise, the run gets a new [Checkpoint] in `options.path_to_runs`,
+    /// which is removed if the run succeeds.
+    #[instrument(name = "job", skip(self, bucket, objects, checkpoint), fields(job = job_name))]
+    async fn run_job_on_bucket<B: bucket::Bucket>(
+        &mut self,
+        job_name: &str,
+        bucket: &B,
+        objects: Option<&[bucket::Object]>,
+        checkpoint: Option<Checkpoint>,
+    ) -> Result<()> {
+        pretty_print::event(&Event::JobStarted { job: job_name })?;
+        let started_at = Utc::now();
+        let timer = Instant::now();
+        let mut stats = JobStats::default();
+        let mut checkpoint = match checkpoint {
+            Some(checkpoint) => checkpoint,
+            None if self.options.is_partial() => Checkpoint::default(),
+            None => Checkpoint::create(&self.options.path_to_runs, job_name),
         };
+        let result = self
+            .try_run_job_on(job_name, bucket, objects, &mut checkpoint, &mut stats)
+            .await;
+        match &result {
+            Ok(()) => checkpoint.remove()?,
+            Err(_) if checkpoint.is_resumable() => pretty_print::status(
+                "Resume",
+                &format!("this run with `csjr resume {}`", checkpoint.run_id()),
+                true,
+            )?,
+            Err(_) => {}
+        }
+        self.finish_run(job_name, started_at, timer.elapsed(), stats, result)
+            .await
+    }
+
+    /// Logs how the run of the job named `job_name` that started at
+    /// `started_at` went, adds it to this runner's [Report] and [Metrics], and
+    /// runs the job's [NotifyHooks]. Returns `result`.
+    async fn finish_run(
+        &mut self,
+        job_name: &str,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        stats: JobStats,
+        result: Result<()>,
+    ) -> Result<()> {
+        pretty_print::event(&Event::JobFinished {
+            job: job_name,
+            succeeded: result.is_ok(),
+            duration,
+        })?;
+        let report = JobReport::new(job_name, started_at, duration, stats, &result);
+        self.metrics.record(
+            &report,
+            self.job(job_name)
+                .ok()
+                .map(|job| &job.cloud_service_provider),
+            result.as_ref().err(),
+        );
+        if let Some(path) = &self.options.path_to_metrics_file {
+            if let Err(e) = self.metrics.write_textfile(path) {
+                pretty_print::error(&e)?;
+            }
+        }
+        if let Ok(job) = self.job(job_name) {
+            if let Err(e) = job.notify.run(&report).await {
+                pretty_print::error(&e)?;
+            }
+        }
+        self.job_reports.push(report);
+        result
+    }
+
+    async fn try_run_job_on<B: bucket::Bucket>(
+        &self,
+        job_name: &str,
+        bucket: &B,
+        objects: Option<&[bucket::Object]>,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+    ) -> Result<()> {
+        let job = self.job(job_name)?;
         let step_runner = step_runner::shell::Runner {};
+        let mut ledger = self.ledger(job_name)?;
+        match objects {
+            Some(objects) => {
+                job.run_on_objects_with_checkpoint(
+                    bucket,
+                    &step_runner,
+                    objects,
+                    &mut ledger,
+                    checkpoint,
+                    stats,
+                    &self.options,
+                )
+                .await
+            }
+            None => {
+                job.run_with_checkpoint(
+                    bucket,
+                    &step_runner,
+                    &mut ledger,
+                    checkpoint,
+                    stats,
+                    &self.options,
+                )
+                .await
+            }
+        }
+    }
 
-        self.print_running_job_status_message(job_name)?;
-        job.run(&bucket, &step_runner).await
+    /// Fetches the [Job] with the name `job_name` from the config.
+    fn job(&self, job_name: &str) -> Result<&'a Job> {
+        self.config.job(job_name)
+    }
+
+    /// Opens the [Ledger] for the job with the name `job_name`.
+    fn ledger(&self, job_name: &str) -> Result<Ledger> {
+        Ledger::open(
+            &self
+                .options
+                .path_to_ledgers
+                .join(format!("{job_name}.jsonl")),
+        )
     }
 
     fn print_running_job_status_message(&mut self, job_name: &str) -> io::Result<()> {
@@ -107,83 +507,629 @@ impl<'a> JobRunner<'a> {
 }
 
 impl Job {
+    /// Returns a [bucket::Bucket] implementation that's authenticated with
+    /// this [Job]'s cloud service provider, and points at its bucket.
+    fn bucket(&self) -> Result<impl bucket::Bucket + '_> {
+        match self.cloud_service_provider {
+            CloudServiceProvider::GCP => bucket::gcp::CloudStorageBucket::new(&self.bucket_name),
+        }
+    }
+
     /// Executes a job, from start to finish.
-    async fn run<B, S>(&self, bucket: &B, step_runner: &S) -> Result<()>
+    ///
+    /// Objects in `path_to_remote_inputs` that `ledger` says have already been
+    /// processed are skipped, unless `options.reprocess` is set. What happens
+    /// along the way is tallied up in `stats`.
+    ///
+    /// If `options.skip_download` is set, the bucket isn't listed at all.
+    pub async fn run<B, S>(
+        &self,
+        bucket: &B,
+        step_runner: &S,
+        ledger: &mut Ledger,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()>
+    where
+        B: bucket::Bucket,
+        S: step_runner::StepRunner,
+    {
+        let mut checkpoint = Checkpoint::default();
+        self.run_with_checkpoint(bucket, step_runner, ledger, &mut checkpoint, stats, options)
+            .await
+    }
+
+    /// Like [Job::run], but records what gets done in `checkpoint`, and skips
+    /// whatever `checkpoint` says a previous attempt at this run already got
+    /// done. If the run has already started, it's run on the same objects as
+    /// before, instead of on what's in the bucket now.
+    #[instrument(skip_all, fields(bucket = self.bucket_name))]
+    pub async fn run_with_checkpoint<B, S>(
+        &self,
+        bucket: &B,
+        step_runner: &S,
+        ledger: &mut Ledger,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()>
+    where
+        B: bucket::Bucket,
+        S: step_runner::StepRunner,
+    {
+        let objects = match checkpoint.objects() {
+            Some(objects) => objects.to_vec(),
+            None if options.skip_download => Vec::new(),
+            None => {
+                let prefix = self
+                    .path_to_remote_inputs
+                    .to_str()
+                    .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+                bucket.list_objects(prefix).await?
+            }
+        };
+        self.run_on_objects_with_checkpoint(
+            bucket,
+            step_runner,
+            &objects,
+            ledger,
+            checkpoint,
+            stats,
+            options,
+        )
+        .await
+    }
+
+    /// Executes a job, but only downloads the provided `objects` instead of
+    /// the entire `path_to_remote_inputs` directory.
+    ///
+    /// Objects that `ledger` says have already been processed are skipped,
+    /// unless `options.reprocess` is set. If every object is skipped, the
+    /// job's steps aren't run at all. Once the job's outputs are uploaded,
+    /// `objects` are recorded in `ledger`, unless `options` leave out part of
+    /// the run. What happens along the way is tallied up in `stats`.
+    ///
+    /// `options` can also skip downloading, uploading, or some of the job's
+    /// steps. Steps are checked against `options.from_step` and
+    /// `options.to_step` before anything is downloaded.
+    pub async fn run_on_objects<B, S>(
+        &self,
+        bucket: &B,
+        step_runner: &S,
+        objects: &[bucket::Object],
+        ledger: &mut Ledger,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()>
+    where
+        B: bucket::Bucket,
+        S: step_runner::StepRunner,
+    {
+        let mut checkpoint = Checkpoint::default();
+        self.run_on_objects_with_checkpoint(
+            bucket,
+            step_runner,
+            objects,
+            ledger,
+            &mut checkpoint,
+            stats,
+            options,
+        )
+        .await
+    }
+
+    /// Like [Job::run_on_objects], but records what gets done in
+    /// `checkpoint`, and skips whatever `checkpoint` says a previous attempt
+    /// at this run already got done: objects that were downloaded (and are
+    /// still on disk), steps that completed, and outputs that were uploaded.
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip_all, fields(num_objects = objects.len(), num_skipped))]
+    pub async fn run_on_objects_with_checkpoint<B, S>(
+        &self,
+        bucket: &B,
+        step_runner: &S,
+        objects: &[bucket::Object],
+        ledger: &mut Ledger,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()>
     where
         B: bucket::Bucket,
         S: step_runner::StepRunner,
     {
-        // TODO: Revisit these unwrap() calls.
-        //
-        // The situation is kinda weird since we're gonna check for an invalid
-        // path in bucket.download_inputs(). Honestly, need to revisit how we're
-        // handling errors to do with paths that can't be serialized as Unicode
-        // strings all across the project. We shouldn't be handling that in the
-        // bucket's impl logic.
+        self.step_range(options)?;
+        checkpoint.record_start(objects)?;
+        if options.skip_download {
+            pretty_print::status(
+                "Skipping",
+                &format!(
+                    "download, and using what's already in \"{}\"",
+                    self.path_to_local_inputs.display()
+                ),
+                true,
+            )?;
+        } else if !self
+            .download_unprocessed(bucket, objects, ledger, checkpoint, stats, options)
+            .await?
+        {
+            stats.skipped = true;
+            return Ok(());
+        }
+        match self.scoped_to_run_with_checkpoint(checkpoint)? {
+            Some(job) => {
+                job.run_steps_and_upload(
+                    bucket,
+                    step_runner,
+                    &self.path_to_remote_outputs,
+                    checkpoint,
+                    stats,
+                    options,
+                )
+                .await?;
+                if !options.skip_upload {
+                    job.update_latest_pointer(bucket, &self.path_to_remote_outputs)
+                        .await?;
+                }
+            }
+            None => {
+                self.run_steps_and_upload(
+                    bucket,
+                    step_runner,
+                    &self.path_to_remote_outputs,
+                    checkpoint,
+                    stats,
+                    options,
+                )
+                .await?
+            }
+        }
+        if options.is_partial() {
+            return Ok(());
+        }
+        ledger.record(objects)
+    }
+
+    /// If `checkpoint` says where this run's outputs go, returns a copy of this
+    /// [Job] with its `path_to_remote_outputs` set to that. Otherwise, works
+    /// like [Job::scoped_to_run], and records where the outputs go in
+    /// `checkpoint`.
+    fn scoped_to_run_with_checkpoint(&self, checkpoint: &mut Checkpoint) -> Result<Option<Job>> {
+        if let Some(path_to_remote_outputs) = checkpoint.path_to_remote_outputs() {
+            return Ok(Some(Job {
+                path_to_remote_outputs: path_to_remote_outputs.into(),
+                ..self.clone()
+            }));
+        }
+        let scoped = self.scoped_to_run(Utc::now())?;
+        if let Some(job) = &scoped {
+            checkpoint.record_outputs_scoped(&job.path_to_remote_outputs.to_string_lossy())?;
+        }
+        Ok(scoped)
+    }
+
+    /// Downloads each of `objects` that `ledger` says hasn't been processed
+    /// yet (or all of them, if `options.reprocess` is set). Objects that
+    /// `checkpoint` says were already downloaded aren't downloaded again, as
+    /// long as they're still on disk. Returns false if every object was
+    /// skipped, so there's nothing to run the job's steps on.
+    async fn download_unprocessed<B: bucket::Bucket>(
+        &self,
+        bucket: &B,
+        objects: &[bucket::Object],
+        ledger: &Ledger,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<bool> {
+        let unprocessed: Vec<&bucket::Object> = objects
+            .iter()
+            .filter(|o| options.reprocess || !ledger.contains(o))
+            .collect();
+        let num_skipped = objects.len() - unprocessed.len();
+        stats.objects_skipped = num_skipped;
+        Span::current().record("num_skipped", num_skipped);
+        for object in objects.iter().filter(|o| !unprocessed.contains(o)) {
+            pretty_print::status_at(
+                Verbosity::Debug,
+                "Skipping",
+                &format!("\"{}\" (generation {})", object.name, object.generation),
+                true,
+            )?;
+        }
+        if num_skipped > 0 {
+            pretty_print::status(
+                "Skipping",
+                &format!("{num_skipped} object(s) that have already been processed"),
+                true,
+            )?;
+            if unprocessed.is_empty() {
+                return Ok(false);
+            }
+        }
+
+        let unprocessed_len = unprocessed.len();
+        let mut not_downloaded = Vec::new();
+        for object in unprocessed {
+            if checkpoint.is_downloaded(&object.name)
+                && keys::local_path(
+                    &object.name,
+                    &self.path_to_remote_inputs,
+                    &self.path_to_local_inputs,
+                )?
+                .exists()
+            {
+                continue;
+            }
+            not_downloaded.push(object);
+        }
+        let num_already_downloaded = unprocessed_len - not_downloaded.len();
+        if num_already_downloaded > 0 {
+            pretty_print::status(
+                "Skipping",
+                &format!("{num_already_downloaded} object(s) that were downloaded before"),
+                true,
+            )?;
+        }
+        if !not_downloaded.is_empty() {
+            self.download_objects(bucket, &not_downloaded, checkpoint, stats)
+                .await?;
+        }
+        Ok(true)
+    }
+
+    /// Downloads each of `objects` into `path_to_local_inputs`, recording each
+    /// one in `checkpoint` once it's been downloaded.
+    #[instrument(skip_all, fields(num_objects = objects.len()))]
+    async fn download_objects<B: bucket::Bucket>(
+        &self,
+        bucket: &B,
+        objects: &[&bucket::Object],
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+    ) -> Result<()> {
         pretty_print::status(
             "Downloading",
             &format!(
-                "\"{}\" to \"{}\"",
-                self.path_to_remote_inputs.to_str().unwrap(),
-                self.path_to_local_inputs.to_str().unwrap()
-            ),
-            true,
-        )?;
-        bucket
-            .download_inputs(&self.path_to_remote_inputs, &self.path_to_local_inputs)
-            .await?;
-        for step in self.get_steps()? {
-            pretty_print::status("Running", &format!("`{step}`"), true)?;
-            step_runner.run_step(&step)?;
-        }
-        // TODO: Same here: revisit these unwrap() calls.
-        //
-        // Same situation as before where bucket.upload_outputs() is handling
-        // invalid paths, but it really shouldn't.
-        pretty_print::status(
-            "Uploading",
-            &format!(
-                "\"{}\" to \"{}\"",
-                self.path_to_local_outputs.to_str().unwrap(),
-                self.path_to_remote_outputs.to_str().unwrap()
+                "{} object(s) from \"{}\" to \"{}\"",
+                objects.len(),
+                self.path_to_remote_inputs.display(),
+                self.path_to_local_inputs.display()
             ),
             true,
         )?;
-        bucket
-            .upload_outputs(&self.path_to_local_outputs, &self.path_to_remote_outputs)
+        let download_started_at = Instant::now();
+        let mut progress = pretty_print::transfer_progress("Downloading", objects.len());
+        for object in objects {
+            pretty_print::event(&Event::DownloadStarted {
+                object: &object.name,
+            })?;
+            let started_at = Instant::now();
+            let num_bytes = bucket
+                .download_object(
+                    &object.name,
+                    &self.path_to_remote_inputs,
+                    &self.path_to_local_inputs,
+                )
+                .await?;
+            checkpoint.record_download(&object.name)?;
+            progress.advance(num_bytes);
+            stats.bytes_downloaded += num_bytes;
+            stats.objects_downloaded += 1;
+            pretty_print::event(&Event::DownloadFinished {
+                object: &object.name,
+                duration: started_at.elapsed(),
+            })?;
+        }
+        progress.finish();
+        stats.download_duration = download_started_at.elapsed();
+        Ok(())
+    }
+
+    /// Runs each of this [Job]'s steps, then uploads its outputs. See
+    /// [Job::upload_outputs] for what `unscoped_path_to_remote_outputs` is.
+    ///
+    /// Bails out with an [InterruptedError] before starting a step, or before
+    /// uploading, if `options.stop_requested` has been set.
+    #[instrument(skip_all, fields(remote_outputs = %self.path_to_remote_outputs.display()))]
+    async fn run_steps_and_upload<B, S>(
+        &self,
+        bucket: &B,
+        step_runner: &S,
+        unscoped_path_to_remote_outputs: &Path,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()>
+    where
+        B: bucket::Bucket,
+        S: step_runner::StepRunner,
+    {
+        self.run_steps(step_runner, checkpoint, stats, options)?;
+        if let Some(&signal) = options.stop_requested.get() {
+            return Err(InterruptedError { signal });
+        }
+        if options.skip_upload {
+            pretty_print::status(
+                "Skipping",
+                &format!("upload to \"{}\"", self.path_to_remote_outputs.display()),
+                true,
+            )?;
+            return Ok(());
+        }
+        self.upload_outputs(bucket, unscoped_path_to_remote_outputs, checkpoint, stats)
             .await
     }
 
+    /// Returns the indexes of the steps that `options` say to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [InvalidStepRangeError] if `options.from_step` or
+    /// `options.to_step` (which start at 1) don't refer to one of this
+    /// [Job]'s steps, or if `options.from_step` comes after `options.to_step`.
+    fn step_range(&self, options: &RunOptions) -> Result<Range<usize>> {
+        let num_steps = self.steps.len();
+        if options.from_step.is_none() && options.to_step.is_none() {
+            return Ok(0..num_steps);
+        }
+        let from_step = options.from_step.unwrap_or(1);
+        let to_step = options.to_step.unwrap_or(num_steps);
+        if from_step < 1 || to_step > num_steps || from_step > to_step {
+            return Err(InvalidStepRangeError {
+                from_step,
+                to_step,
+                num_steps,
+            });
+        }
+        Ok(from_step - 1..to_step)
+    }
+
+    /// Runs each of this [Job]'s steps, in order, except for the ones that
+    /// `checkpoint` says already completed. Each step that completes is
+    /// recorded in `checkpoint`.
+    ///
+    /// Bails out with an [InterruptedError] before starting a step if
+    /// `options.stop_requested` has been set.
+    fn run_steps<S: step_runner::StepRunner>(
+        &self,
+        step_runner: &S,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+        options: &RunOptions,
+    ) -> Result<()> {
+        let step_range = self.step_range(options)?;
+        for (index, step) in self.get_steps().iter().enumerate() {
+            if !step_range.contains(&index) || checkpoint.is_step_completed(index) {
+                pretty_print::status("Skipping", &format!("`{step}`"), true)?;
+                continue;
+            }
+            if let Some(&signal) = options.stop_requested.get() {
+                return Err(InterruptedError { signal });
+            }
+            let step_as_string = step.to_string();
+            let step_as_str = step_as_string.as_str();
+            pretty_print::event(&Event::StepStarted {
+                index,
+                step: step_as_str,
+            })?;
+            let started_at = Instant::now();
+            let progress = pretty_print::step_progress(step_as_str);
+            let result = info_span!("step", index, step = step_as_str)
+                .in_scope(|| step_runner.run_step(step));
+            progress.finish();
+            let exit_code = match &result {
+                Ok(()) => Some(0),
+                Err(StepNonZeroStatusCodeError { code, .. }) => *code,
+                Err(_) => None,
+            };
+            pretty_print::event(&Event::StepFinished {
+                index,
+                step: step_as_str,
+                exit_code,
+                duration: started_at.elapsed(),
+            })?;
+            stats.steps_duration += started_at.elapsed();
+            stats.step_durations.push((index, started_at.elapsed()));
+            result?;
+            checkpoint.record_step(index)?;
+        }
+        Ok(())
+    }
+
+    /// Uploads everything in this [Job]'s `path_to_local_outputs` directory
+    /// to `path_to_remote_outputs`, publishing it atomically if the job has
+    /// `atomic_outputs` set.
+    ///
+    /// Outputs that `checkpoint` says were already uploaded aren't uploaded
+    /// again, unless they're published atomically, in which case all of them
+    /// are. `unscoped_path_to_remote_outputs` is `path_to_remote_outputs`
+    /// before its run identifiers were filled in, if it had any.
+    async fn upload_outputs<B: bucket::Bucket>(
+        &self,
+        bucket: &B,
+        unscoped_path_to_remote_outputs: &Path,
+        checkpoint: &mut Checkpoint,
+        stats: &mut JobStats,
+    ) -> Result<()> {
+        let from = self.path_to_local_outputs.display().to_string();
+        let to = self.path_to_remote_outputs.display().to_string();
+        pretty_print::event(&Event::UploadStarted {
+            from: &from,
+            to: &to,
+        })?;
+        let started_at = Instant::now();
+        (stats.objects_uploaded, stats.bytes_uploaded) = if self.atomic_outputs {
+            self.publish_outputs(bucket, unscoped_path_to_remote_outputs)
+                .await?
+        } else {
+            self.upload_outputs_not_yet_uploaded(bucket, checkpoint)
+                .await?
+        };
+        stats.upload_duration = started_at.elapsed();
+        stats.remote_outputs = Some(to.clone());
+        pretty_print::event(&Event::UploadFinished {
+            from: &from,
+            to: &to,
+            duration: started_at.elapsed(),
+        })?;
+        Ok(())
+    }
+
+    /// Uploads each file in this [Job]'s `path_to_local_outputs` directory
+    /// that `checkpoint` says hasn't been uploaded yet, recording each one in
+    /// `checkpoint` once it has. Returns the number of files and bytes
+    /// uploaded.
+    async fn upload_outputs_not_yet_uploaded<B: bucket::Bucket>(
+        &self,
+        bucket: &B,
+        checkpoint: &mut Checkpoint,
+    ) -> Result<(usize, u64)> {
+        let files = bucket::find_all_files(&self.path_to_local_outputs)?;
+        let mut progress = pretty_print::transfer_progress("Uploading", files.len());
+        let mut objects_uploaded = 0;
+        let mut bytes_uploaded = 0;
+        for file in files {
+            let object_name = keys::object_name(
+                &file,
+                &self.path_to_local_outputs,
+                &self.path_to_remote_outputs,
+            )?;
+            if checkpoint.is_uploaded(&object_name) {
+                pretty_print::status_at(
+                    Verbosity::Debug,
+                    "Skipping",
+                    &format!("\"{object_name}\", which was already uploaded"),
+                    true,
+                )?;
+                progress.advance(0);
+                continue;
+            }
+            let num_bytes = bucket
+                .upload_object(
+                    &file,
+                    &self.path_to_local_outputs,
+                    &self.path_to_remote_outputs,
+                )
+                .await?;
+            checkpoint.record_upload(&object_name)?;
+            progress.advance(num_bytes);
+            pretty_print::status_at(
+                Verbosity::Verbose,
+                "Uploaded",
+                &format!("\"{}\"", file.display()),
+                true,
+            )?;
+            objects_uploaded += 1;
+            bytes_uploaded += num_bytes;
+        }
+        progress.finish();
+        Ok((objects_uploaded, bytes_uploaded))
+    }
+
     /// Returns a list of this [Job]'s steps with all of the `[path_to_*_*]`
     /// tags substituted with their corresponding values.
-    fn get_steps(&self) -> Result<Vec<String>> {
-        let path_to_remote_inputs_as_string = self
-            .path_to_remote_inputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
-        let path_to_local_inputs_as_string = self
-            .path_to_local_inputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::LocalInputs))?;
-        let path_to_local_outputs_as_string = self
-            .path_to_local_outputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::LocalOutputs))?;
-        let path_to_remote_outputs_as_string = self
-            .path_to_remote_outputs
-            .to_str()
-            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
-
-        let mut steps = Vec::new();
-        for step in &self.steps {
-            // TODO: Revisit this. Is there a more modular way?
-            let s = step
-                .replace("[path_to_remote_inputs]", path_to_remote_inputs_as_string)
-                .replace("[path_to_local_inputs]", path_to_local_inputs_as_string)
-                .replace("[path_to_local_outputs]", path_to_local_outputs_as_string)
-                .replace("[path_to_remote_outputs]", path_to_remote_outputs_as_string);
-            steps.push(s);
-        }
-        Ok(steps)
+    ///
+    /// Steps are split into words before tags are substituted, so each path is
+    /// passed to a step as a single argument, even if it contains spaces or
+    /// isn't valid UTF-8.
+    fn get_steps(&self) -> Vec<Step> {
+        let tags = [
+            (
+                "[path_to_remote_inputs]",
+                self.path_to_remote_inputs.as_os_str(),
+            ),
+            (
+                "[path_to_local_inputs]",
+                self.path_to_local_inputs.as_os_str(),
+            ),
+            (
+                "[path_to_local_outputs]",
+                self.path_to_local_outputs.as_os_str(),
+            ),
+            (
+                "[path_to_remote_outputs]",
+                self.path_to_remote_outputs.as_os_str(),
+            ),
+        ];
+        self.steps
+            .iter()
+            .map(|step| {
+                Step::new(
+                    step.split(' ')
+                        .map(|word| substitute_tags(word, &tags))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns `word` with each of the `tags` in it replaced with its value.
+fn substitute_tags(word: &str, tags: &[(&str, &OsStr)]) -> OsString {
+    let mut substituted = OsString::new();
+    let mut rest = word;
+    while let Some((start, tag, value)) = tags
+        .iter()
+        .filter_map(|&(tag, value)| rest.find(tag).map(|start| (start, tag, value)))
+        .min_by_key(|&(start, ..)| start)
+    {
+        substituted.push(&rest[..start]);
+        substituted.push(value);
+        rest = &rest[start + tag.len()..];
+    }
+    substituted.push(rest);
+    substituted
+}
+
+#[cfg(test)]
+mod substitute_tags_tests {
+    use super::substitute_tags;
+    use std::ffi::{OsStr, OsString};
+
+    const TAGS: [(&str, &str); 2] = [
+        ("[path_to_local_inputs]", "in put"),
+        ("[path_to_local_outputs]", "out"),
+    ];
+
+    fn substitute(word: &str) -> OsString {
+        let tags: Vec<(&str, &OsStr)> = TAGS
+            .iter()
+            .map(|&(tag, value)| (tag, OsStr::new(value)))
+            .collect();
+        substitute_tags(word, &tags)
+    }
+
+    #[test]
+    fn word_without_tags() {
+        assert_eq!(substitute("--verbose"), "--verbose");
+    }
+
+    #[test]
+    fn word_is_a_tag() {
+        assert_eq!(substitute("[path_to_local_inputs]"), "in put");
+    }
+
+    #[test]
+    fn word_contains_tags() {
+        assert_eq!(
+            substitute(
+                "--dirs=[path_to_local_inputs],[path_to_local_outputs],[path_to_local_inputs]"
+            ),
+            "--dirs=in put,out,in put"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn value_is_not_unicode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let value = OsStr::from_bytes(b"caf\xe9");
+        let substituted = substitute_tags(
+            "[path_to_local_inputs]/x",
+            &[("[path_to_local_inputs]", value)],
+        );
+        assert_eq!(substituted.as_bytes(), b"caf\xe9/x");
     }
 }
@@ -0,0 +1,230 @@
+use crate::{
+    bucket::{Bucket, ObjectDetails},
+    errors::JobRunnerError::InvalidPathError,
+    versioning, Job, JobRunner, PathKeyInConfig, Result,
+};
+use chrono::SecondsFormat;
+use std::io::{self, Write};
+
+/// Which of a job's bucket's objects `csjr ls` lists.
+#[derive(Debug, Clone)]
+pub enum ListTarget {
+    /// The objects in the job's `path_to_remote_inputs` directory.
+    Inputs,
+    /// The objects in the job's `path_to_remote_outputs` directory. If it has
+    /// run identifiers in it, the objects in the directory that contains the
+    /// first one, so that every run's outputs are listed.
+    Outputs,
+    /// The objects in some other directory in the job's bucket.
+    Prefix(String),
+}
+
+impl ListTarget {
+    /// Returns the prefix in `job`'s bucket that this target lists.
+    fn prefix(&self, job: &Job) -> Result<String> {
+        let prefix = match self {
+            ListTarget::Inputs => job
+                .path_to_remote_inputs
+                .to_str()
+                .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?,
+            ListTarget::Outputs => {
+                return versioning::unscoped_prefix(&job.path_to_remote_outputs)
+                    .to_str()
+                    .map(String::from)
+                    .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))
+            }
+            ListTarget::Prefix(prefix) => prefix,
+        };
+        Ok(prefix.into())
+    }
+}
+
+/// How `csjr ls` writes the objects it lists to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListFormat {
+    /// Just each object's name.
+    Short,
+    /// Each object's size, when it was last updated, its content type, and its
+    /// name, followed by how many objects and bytes there are in total.
+    Long,
+    /// One JSON object per line for each object.
+    Json,
+}
+
+impl<'a> JobRunner<'a> {
+    /// Lists the objects in `target` in the bucket of the job named
+    /// `job_name`, and writes them to stdout in `format`.
+    pub async fn ls(&self, job_name: &str, target: &ListTarget, format: ListFormat) -> Result<()> {
+        let job = self.job(job_name)?;
+        let prefix = target.prefix(job)?;
+        let objects = job.bucket()?.list_object_details(&prefix).await?;
+        write_listing(&mut io::stdout().lock(), &objects, format)?;
+        Ok(())
+    }
+}
+
+/// Writes `objects` to `w` in `format`.
+fn write_listing(
+    w: &mut impl Write,
+    objects: &[ObjectDetails],
+    format: ListFormat,
+) -> io::Result<()> {
+    match format {
+        ListFormat::Short => {
+            for object in objects {
+                writeln!(w, "{}", object.name)?;
+            }
+        }
+        ListFormat::Long => {
+            let content_type_width = objects
+                .iter()
+                .map(|object| content_type(object).len())
+                .max()
+                .unwrap_or_default();
+            for object in objects {
+                writeln!(
+                    w,
+                    "{:>12}  {}  {:<content_type_width$}  {}",
+                    object.size,
+                    object.updated.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    content_type(object),
+                    object.name
+                )?;
+            }
+            let num_bytes: u64 = objects.iter().map(|object| object.size).sum();
+            writeln!(w, "TOTAL: {} object(s), {num_bytes} bytes", objects.len())?;
+        }
+        ListFormat::Json => {
+            for object in objects {
+                serde_json::to_writer(&mut *w, object)?;
+                writeln!(w)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns `object`'s content type, or `-` if it doesn't have one.
+fn content_type(object: &ObjectDetails) -> &str {
+    object.content_type.as_deref().unwrap_or("-")
+}
+
+#[cfg(test)]
+mod write_listing_tests {
+    use super::{write_listing, ListFormat};
+    use crate::bucket::ObjectDetails;
+    use chrono::{TimeZone, Utc};
+
+    fn objects() -> Vec<ObjectDetails> {
+        vec![
+            ObjectDetails {
+                name: "img/cat.png".into(),
+                size: 2048,
+                updated: Utc.with_ymd_and_hms(2022, 4, 18, 2, 0, 0).unwrap(),
+                content_type: Some("image/png".into()),
+            },
+            ObjectDetails {
+                name: "img/notes".into(),
+                size: 12,
+                updated: Utc.with_ymd_and_hms(2022, 4, 19, 13, 30, 5).unwrap(),
+                content_type: None,
+            },
+        ]
+    }
+
+    fn listing(format: ListFormat) -> String {
+        let mut w = Vec::new();
+        write_listing(&mut w, &objects(), format).unwrap();
+        String::from_utf8(w).unwrap()
+    }
+
+    #[test]
+    fn short() {
+        assert_eq!(listing(ListFormat::Short), "img/cat.png\nimg/notes\n");
+    }
+
+    #[test]
+    fn long() {
+        assert_eq!(
+            listing(ListFormat::Long),
+            "        2048  2022-04-18T02:00:00Z  image/png  img/cat.png\n\
+             \x20         12  2022-04-19T13:30:05Z  -          img/notes\n\
+             TOTAL: 2 object(s), 2060 bytes\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        let listing = listing(ListFormat::Json);
+        let lines: Vec<serde_json::Value> = listing
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["name"], "img/cat.png");
+        assert_eq!(lines[0]["size"], 2048);
+        assert_eq!(lines[0]["updated"], "2022-04-18T02:00:00Z");
+        assert_eq!(lines[0]["content_type"], "image/png");
+        assert_eq!(lines[1]["content_type"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn long_without_objects() {
+        let mut w = Vec::new();
+        write_listing(&mut w, &[], ListFormat::Long).unwrap();
+        assert_eq!(
+            String::from_utf8(w).unwrap(),
+            "TOTAL: 0 object(s), 0 bytes\n"
+        );
+    }
+}
+
+#[cfg(test)]
+mod list_target_tests {
+    use super::ListTarget;
+    use crate::{CloudServiceProvider, Job, NotifyHooks};
+
+    fn job(path_to_remote_outputs: &str) -> Job {
+        Job {
+            cloud_service_provider: CloudServiceProvider::GCP,
+            bucket_name: "foo".into(),
+            path_to_remote_inputs: "img".into(),
+            path_to_local_inputs: "inputs".into(),
+            path_to_local_outputs: "outputs".into(),
+            path_to_remote_outputs: path_to_remote_outputs.into(),
+            steps: vec![],
+            schedule: None,
+            atomic_outputs: false,
+            notify: NotifyHooks::default(),
+        }
+    }
+
+    #[test]
+    fn outputs_without_run_identifiers() {
+        assert_eq!(
+            ListTarget::Outputs.prefix(&job("results/seg")).unwrap(),
+            "results/seg"
+        );
+    }
+
+    #[test]
+    fn outputs_with_run_identifiers_list_every_run() {
+        assert_eq!(
+            ListTarget::Outputs
+                .prefix(&job("results/seg/[timestamp]"))
+                .unwrap(),
+            "results/seg"
+        );
+    }
+
+    #[test]
+    fn inputs_and_other_prefixes() {
+        assert_eq!(ListTarget::Inputs.prefix(&job("bar")).unwrap(), "img");
+        assert_eq!(
+            ListTarget::Prefix("logs".into())
+                .prefix(&job("bar"))
+                .unwrap(),
+            "logs"
+        );
+    }
+}
@@ -1,6 +1,30 @@
-use cloud_storage_job_runner::{pretty_print, Config, JobRunner};
-use std::{fs::File, path::PathBuf, process};
-use structopt::StructOpt;
+#[cfg(feature = "otel")]
+use cloud_storage_job_runner::telemetry::Telemetry;
+use cloud_storage_job_runner::{
+    completions, exit_code,
+    init::{self, Scaffold},
+    ls::{ListFormat, ListTarget},
+    metrics,
+    notification::JsonLinesSource,
+    pretty_print::{self, ColorMode, LogFormat, Settings, Verbosity},
+    report::Report,
+    Config, JobRunner, JobRunnerError, RunOptions, CONFIG_FILE_NAME,
+};
+use std::{
+    env,
+    error::Error,
+    ffi::OsString,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process,
+    time::Duration,
+};
+use structopt::{
+    clap::{self, Shell},
+    StructOpt,
+};
+use tokio::io::BufReader;
 
 #[derive(Debug, StructOpt)]
 #[structopt(
@@ -10,41 +34,482 @@ use structopt::StructOpt;
     author = env!("CARGO_PKG_AUTHORS"),
 )]
 struct Opt {
-    /// Path to config file
-    #[structopt(short, long, parse(from_os_str))]
-    config: PathBuf,
+    /// Path to config file. If not present, uses the closest csjr.yaml in the current directory or its parents, or else $XDG_CONFIG_HOME/csjr/config.yaml. `init` writes a new one here (csjr.yaml by default)
+    #[structopt(short, long, env = "CSJR_CONFIG", parse(from_os_str))]
+    config: Option<PathBuf>,
     /// Name of job to run. If not present, runs all jobs specified in the provided config file
     #[structopt()]
     job_name: Option<String>,
+    /// Directory where records of which objects each job has already processed are kept
+    #[structopt(long, parse(from_os_str), default_value = ".csjr/ledger")]
+    ledger_dir: PathBuf,
+    /// Directory where records of what each run that hasn't succeeded got done are kept, so that it can be resumed
+    #[structopt(long, parse(from_os_str), default_value = ".csjr/runs")]
+    runs_dir: PathBuf,
+    /// Run jobs on objects even if they've already been processed
+    #[structopt(long)]
+    reprocess: bool,
+    /// Number (starting at 1) of the first step to run. Earlier steps are skipped
+    #[structopt(long, requires = "job-name")]
+    from_step: Option<usize>,
+    /// Number (starting at 1) of the last step to run. Later steps are skipped
+    #[structopt(long, requires = "job-name")]
+    to_step: Option<usize>,
+    /// Don't download anything, and run steps on whatever is already in the job's local inputs
+    #[structopt(long, requires = "job-name")]
+    skip_download: bool,
+    /// Don't upload anything once steps have run
+    #[structopt(long, requires = "job-name")]
+    skip_upload: bool,
+    /// How to write status messages and errors: "human" for colored text, or "json" for one JSON object per event
+    #[structopt(long, default_value = "human")]
+    log_format: LogFormat,
+    /// Don't print anything but errors
+    #[structopt(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print more detail, like each object that's transferred. Pass twice (-vv) to also print debugging details, like the exact command line each step is run with
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u64,
+    /// When to color output: "auto" (only in a terminal, and if NO_COLOR isn't set), "always", or "never"
+    #[structopt(long, default_value = "auto")]
+    color: ColorMode,
+    /// Path to write a JSON report of how each job went to, once csjr is done running jobs
+    #[structopt(long, parse(from_os_str))]
+    report: Option<PathBuf>,
+    /// Path to write a JUnit XML report of how each job went to, once csjr is done running jobs
+    #[structopt(long, parse(from_os_str))]
+    junit_report: Option<PathBuf>,
+    /// Address (like 127.0.0.1:9898) to serve Prometheus metrics about the jobs that have been run at, under /metrics
+    #[structopt(long)]
+    metrics_address: Option<SocketAddr>,
+    /// Path to write Prometheus metrics about the jobs that have been run to after each job, for node_exporter's textfile collector
+    #[structopt(long, parse(from_os_str))]
+    metrics_file: Option<PathBuf>,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands that don't need a config file.
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Write a starter config file, with comments explaining what each key is for
+    Init {
+        /// Ask for the job's name, bucket, and paths, and check that its remote inputs can be listed
+        #[structopt(short, long)]
+        interactive: bool,
+        /// Overwrite the config file if it already exists
+        #[structopt(long)]
+        force: bool,
+    },
+    /// Print a script that completes csjr's subcommands, options, and job names in a shell. For bash, add `eval "$(csjr completions bash)"` to ~/.bashrc
+    Completions {
+        /// Shell to print completions for
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        shell: Shell,
+    },
+    #[structopt(flatten)]
+    WithConfig(ConfigCommand),
+}
+
+/// Subcommands that run (or look at) the jobs in a config file.
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Stay running in the foreground, and run each job that has a schedule whenever its schedule says to
+    Daemon,
+    /// Run jobs on the objects that notifications (GCS Pub/Sub or S3 event notifications, one JSON message per line) say were created
+    Listen {
+        /// Path to a file to read notifications from. If not present, reads notifications from stdin
+        #[structopt(short, long, parse(from_os_str))]
+        file: Option<PathBuf>,
+    },
+    /// List the jobs in the config file, along with each one's bucket, remote inputs and outputs, and how many steps it has
+    Jobs {
+        /// Only print each job's name, one per line
+        #[structopt(long)]
+        names: bool,
+    },
+    /// Print a job the way csjr sees it, with defaults filled in and the paths in its steps substituted
+    Show {
+        /// Name of the job to print
+        job_name: String,
+    },
+    /// List the objects in a job's remote inputs (or outputs, or some other directory in its bucket)
+    Ls {
+        /// Name of the job whose bucket to list objects in
+        job_name: String,
+        /// List the objects in the job's remote outputs instead of its remote inputs
+        #[structopt(long, conflicts_with = "prefix")]
+        outputs: bool,
+        /// List the objects in this directory in the job's bucket instead of its remote inputs
+        #[structopt(long)]
+        prefix: Option<String>,
+        /// Also show each object's size in bytes, when it was last updated, and its content type
+        #[structopt(short, long, conflicts_with = "json")]
+        long: bool,
+        /// Write each object's details as a JSON object, one per line
+        #[structopt(long)]
+        json: bool,
+    },
+    /// Download a job's remote inputs to its local inputs, without running any of its steps
+    Pull {
+        /// Name of the job whose inputs to download
+        job_name: String,
+    },
+    /// Upload a job's local outputs to its remote outputs, without running any of its steps
+    Push {
+        /// Name of the job whose outputs to upload
+        job_name: String,
+    },
+    /// Continue a run that was interrupted or failed where it left off, without redoing what it already got done
+    Resume {
+        /// ID of the run to resume, which csjr prints when a run is interrupted or fails
+        run_id: String,
+    },
+    /// Periodically check jobs' remote inputs for new objects, and run those jobs on just the new objects
+    Watch {
+        /// Names of jobs to watch. If not present, watches all jobs specified in the provided config file
+        job_names: Vec<String>,
+        /// Number of seconds to wait between checks for new objects
+        #[structopt(short, long, default_value = "60")]
+        interval: u64,
+        /// Don't run jobs on objects that are already in the bucket when watching starts, and record them in the jobs' ledgers as processed
+        #[structopt(long)]
+        skip_existing: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() {
-    let opt = Opt::from_args();
-
-    // TODO: Display friendlier error messages instead of panicking in these
-    // situations.
-    let config_file = File::open(opt.config).expect("config file couldn't be opened");
-    let config: Config =
-        serde_yaml::from_reader(config_file).expect("config file's contents are invalid");
-    let mut job_runner = JobRunner::new(&config);
-
-    match opt.job_name {
-        Some(j) => {
-            if let Err(e) = job_runner.run_one(&j).await {
-                if pretty_print::error(&e).is_err() {
-                    eprintln!("Something went wrong displaying an error message");
-                }
-                process::exit(1);
+    let opt = parse_args(env::args_os().collect()).unwrap_or_else(|e| e.exit());
+    pretty_print::configure(Settings {
+        log_format: opt.log_format,
+        verbosity: Verbosity::from_flags(opt.quiet, opt.verbose),
+        color: opt.color,
+    })
+    .expect("csjr's output is only configured once");
+    #[cfg(feature = "otel")]
+    let telemetry = match Telemetry::init() {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            print_error(e.as_ref());
+            process::exit(exit_code::OTHER);
+        }
+    };
+
+    // These don't need a config, so they're run before one is looked for.
+    let command = match opt.command {
+        Some(Command::Completions { shell }) => {
+            if let Err(e) = print_completions(shell) {
+                print_error(&e);
+                process::exit(exit_code::IO);
             }
+            return;
         }
-        None => {
-            if let Err(e) = job_runner.run_all().await {
-                if pretty_print::error(&e).is_err() {
-                    eprintln!("Something went wrong displaying an error message");
+        Some(Command::Init { interactive, force }) => {
+            let path_to_config = opt.config.unwrap_or_else(|| CONFIG_FILE_NAME.into());
+            if let Err(e) = init(&path_to_config, interactive, force).await {
+                print_error(&e);
+                process::exit(e.exit_code());
+            }
+            return;
+        }
+        Some(Command::WithConfig(command)) => Some(command),
+        None => None,
+    };
+    let path_to_config = match opt.config.map_or_else(Config::find, Ok) {
+        Ok(path) => path,
+        Err(e) => {
+            print_error(&e);
+            process::exit(e.exit_code());
+        }
+    };
+    let _ = pretty_print::status_at(
+        Verbosity::Verbose,
+        "Using",
+        &format!("config \"{}\"", path_to_config.display()),
+        false,
+    );
+    let config = match Config::from_path(&path_to_config) {
+        Ok(config) => config,
+        Err(e) => {
+            print_error(&e);
+            process::exit(e.exit_code());
+        }
+    };
+    let mut shadowed_job_names: Vec<&String> = config
+        .jobs
+        .keys()
+        .filter(|job_name| is_subcommand(job_name))
+        .collect();
+    shadowed_job_names.sort();
+    for job_name in shadowed_job_names {
+        let _ = pretty_print::status(
+            "Warning",
+            &format!(
+                "`csjr {job_name}` runs the {job_name} subcommand, not the job named \"{job_name}\". Run the job with `csjr -- {job_name}` instead, or rename it"
+            ),
+            false,
+        );
+    }
+    let mut job_runner = JobRunner::new(
+        &config,
+        RunOptions {
+            path_to_ledgers: opt.ledger_dir,
+            path_to_runs: opt.runs_dir,
+            reprocess: opt.reprocess,
+            path_to_metrics_file: opt.metrics_file,
+            from_step: opt.from_step,
+            to_step: opt.to_step,
+            skip_download: opt.skip_download,
+            skip_upload: opt.skip_upload,
+            ..Default::default()
+        },
+    );
+
+    if let Some(address) = opt.metrics_address {
+        match metrics::serve(address, job_runner.metrics()).await {
+            Ok(server) => {
+                tokio::spawn(async move {
+                    if let Err(e) = server.await {
+                        let _ = pretty_print::error(&e);
+                    }
+                });
+            }
+            Err(e) => {
+                print_error(&e);
+                process::exit(e.exit_code());
+            }
+        }
+    }
+
+    let result: Result<(), Box<dyn Error>> = match (command, opt.job_name) {
+        (
+            Some(ConfigCommand::Watch {
+                job_names,
+                interval,
+                skip_existing,
+            }),
+            _,
+        ) => job_runner
+            .watch(&job_names, Duration::from_secs(interval), skip_existing)
+            .await
+            .map_err(Box::from),
+        (Some(ConfigCommand::Daemon), _) => job_runner.daemon().await.map_err(Box::from),
+        (Some(ConfigCommand::Listen { file }), _) => listen(&mut job_runner, file).await,
+        (
+            Some(ConfigCommand::Ls {
+                job_name,
+                outputs,
+                prefix,
+                long,
+                json,
+            }),
+            _,
+        ) => {
+            let target = match prefix {
+                Some(prefix) => ListTarget::Prefix(prefix),
+                None if outputs => ListTarget::Outputs,
+                None => ListTarget::Inputs,
+            };
+            let format = if json {
+                ListFormat::Json
+            } else if long {
+                ListFormat::Long
+            } else {
+                ListFormat::Short
+            };
+            job_runner
+                .ls(&job_name, &target, format)
+                .await
+                .map_err(Box::from)
+        }
+        (Some(ConfigCommand::Jobs { names }), _) => job_runner.jobs(names).map_err(Box::from),
+        (Some(ConfigCommand::Show { job_name }), _) => {
+            job_runner.show(&job_name).map_err(Box::from)
+        }
+        (Some(ConfigCommand::Pull { job_name }), _) => {
+            job_runner.pull(&job_name).await.map_err(Box::from)
+        }
+        (Some(ConfigCommand::Push { job_name }), _) => {
+            job_runner.push(&job_name).await.map_err(Box::from)
+        }
+        (Some(ConfigCommand::Resume { run_id }), _) => {
+            job_runner.resume(&run_id).await.map_err(Box::from)
+        }
+        (None, Some(j)) => job_runner.run_one(&j).await.map_err(Box::from),
+        (None, None) => job_runner.run_all().await,
+    };
+    let report_result = write_reports(
+        &job_runner.report(),
+        opt.report.as_deref(),
+        opt.junit_report.as_deref(),
+    );
+
+    // Not being able to export traces shouldn't fail a run that otherwise
+    // went fine, so this is reported, but doesn't change the exit code.
+    #[cfg(feature = "otel")]
+    if let Err(e) = telemetry.shutdown() {
+        print_error(e.as_ref());
+    }
+
+    // If more than one thing went wrong, the first one decides the exit code.
+    let mut code = None;
+    for e in result.err().into_iter().chain(report_result.err()) {
+        print_error(e.as_ref());
+        code = code.or(Some(exit_code_for(e.as_ref())));
+    }
+    if let Some(code) = code {
+        process::exit(code);
+    }
+}
+
+/// Parses csjr's command line `args`.
+///
+/// clap treats a job name that's also the name of a subcommand as that
+/// subcommand, even after `--`. So if `args` don't parse, but end in
+/// `-- <job name>`, they're parsed again without those, and the job name is
+/// filled in afterwards.
+fn parse_args(args: Vec<OsString>) -> Result<Opt, clap::Error> {
+    let err = match Opt::from_iter_safe(&args) {
+        Ok(opt) => return Ok(opt),
+        Err(err) => err,
+    };
+    if let [rest @ .., dashes, job_name] = args.as_slice() {
+        if dashes == "--" {
+            if let (Ok(mut opt), Some(job_name)) = (Opt::from_iter_safe(rest), job_name.to_str()) {
+                if opt.command.is_none() && opt.job_name.is_none() {
+                    opt.job_name = Some(job_name.into());
+                    return Ok(opt);
                 }
-                process::exit(1);
             }
         }
+    }
+    Err(err)
+}
+
+/// Returns true if `csjr <name>` runs the subcommand called `name`.
+fn is_subcommand(name: &str) -> bool {
+    // clap adds the help subcommand itself, once it starts parsing.
+    name == "help"
+        || Opt::clap()
+            .p
+            .subcommands
+            .iter()
+            .any(|subcommand| subcommand.get_name() == name)
+}
+
+/// Prints a script to stdout that completes csjr's subcommands and options in
+/// `shell`.
+fn print_completions(shell: Shell) -> io::Result<()> {
+    completions::generate(Opt::clap(), shell, "csjr", &mut io::stdout())
+}
+
+fn print_error(err: &dyn Error) {
+    if pretty_print::error(err).is_err() {
+        eprintln!("Something went wrong displaying an error message");
+    }
+}
+
+/// Returns the code that csjr should exit with because of `err`. See
+/// [exit_code] for what each code means.
+fn exit_code_for(err: &(dyn Error + 'static)) -> i32 {
+    if let Some(err) = err.downcast_ref::<JobRunnerError>() {
+        err.exit_code()
+    } else if err.is::<io::Error>() {
+        exit_code::IO
+    } else {
+        exit_code::OTHER
+    }
+}
+
+/// Writes `report` as JSON to `path_to_json`, and as JUnit XML to
+/// `path_to_junit`, for whichever of those paths were provided.
+fn write_reports(
+    report: &Report,
+    path_to_json: Option<&Path>,
+    path_to_junit: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(path) = path_to_json {
+        report.write_json(path)?;
+    }
+    if let Some(path) = path_to_junit {
+        report.write_junit(path)?;
+    }
+    Ok(())
+}
+
+/// Writes a starter config file to `path`. If `interactive` is set, asks for
+/// the values to fill it in with on stdin first, and then checks that the job
+/// in it can list its remote inputs.
+async fn init(path: &Path, interactive: bool, force: bool) -> Result<(), JobRunnerError> {
+    // Checked before asking anything, so nobody answers every question only
+    // to be told that there's already a config there.
+    if path.exists() && !force {
+        return Err(JobRunnerError::ConfigExistsError { path: path.into() });
+    }
+    let scaffold = if interactive {
+        Scaffold::prompt(&mut io::stdin().lock(), &mut io::stderr())?
+    } else {
+        Scaffold::default()
     };
+    init::write_config(path, &scaffold.render(), force)?;
+    if interactive {
+        init::check_access(path, &scaffold.job_name).await?;
+    }
+    Ok(())
+}
+
+/// Runs jobs on the objects that notifications read from `file` (or stdin, if
+/// `file` is `None`) are about.
+async fn listen(
+    job_runner: &mut JobRunner<'_>,
+    file: Option<PathBuf>,
+) -> Result<(), Box<dyn Error>> {
+    match file {
+        Some(file) => {
+            let file = tokio::fs::File::open(file).await?;
+            let mut source = JsonLinesSource::new(BufReader::new(file));
+            job_runner.listen(&mut source).await?;
+        }
+        None => {
+            let mut source = JsonLinesSource::new(BufReader::new(tokio::io::stdin()));
+            job_runner.listen(&mut source).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod parse_args_tests {
+    use super::{parse_args, Command, ConfigCommand};
+
+    fn args(args: &[&str]) -> Vec<std::ffi::OsString> {
+        args.iter().map(Into::into).collect()
+    }
+
+    #[test]
+    fn job_named_after_subcommand_is_run_after_dashes() {
+        let opt = parse_args(args(&["csjr", "-c", "csjr.yaml", "--", "push"])).unwrap();
+
+        assert!(opt.command.is_none());
+        assert_eq!(opt.job_name.as_deref(), Some("push"));
+    }
+
+    #[test]
+    fn subcommand_arguments_after_dashes_are_left_alone() {
+        let opt = parse_args(args(&["csjr", "push", "--", "resize"])).unwrap();
+
+        assert!(matches!(
+            opt.command,
+            Some(Command::WithConfig(ConfigCommand::Push { job_name })) if job_name == "resize"
+        ));
+    }
+
+    #[test]
+    fn subcommands_are_recognized() {
+        for name in ["completions", "help", "init", "jobs", "ls", "push", "watch"] {
+            assert!(super::is_subcommand(name), "{name} is a subcommand");
+        }
+        assert!(!super::is_subcommand("resize"));
+    }
 }
@@ -0,0 +1,459 @@
+use crate::{
+    errors::JobRunnerError::{self, ReportError},
+    report::{JobReport, JobStatus},
+    CloudServiceProvider, Result,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+};
+
+/// Upper bounds, in seconds, of the buckets that durations are sorted into.
+/// Jobs that run nightly can take anywhere from seconds to hours.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0, 300.0, 900.0, 1800.0, 3600.0, 10800.0,
+];
+
+/// Counters and histograms about every job that a [crate::JobRunner] has run,
+/// which can be exposed to Prometheus.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// Keyed by job name and status.
+    jobs: BTreeMap<(String, &'static str), u64>,
+    /// Keyed by job name.
+    job_durations: BTreeMap<String, Histogram>,
+    /// Keyed by job name and step index.
+    step_durations: BTreeMap<(String, usize), Histogram>,
+    /// Keyed by job name and provider.
+    objects_downloaded: BTreeMap<(String, &'static str), u64>,
+    bytes_downloaded: BTreeMap<(String, &'static str), u64>,
+    bytes_uploaded: BTreeMap<(String, &'static str), u64>,
+    /// Keyed by provider and the operation that failed.
+    bucket_errors: BTreeMap<(&'static str, &'static str), u64>,
+    /// Unix timestamps of the last time each job succeeded, keyed by job name.
+    last_success: BTreeMap<String, i64>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// How many observations fell into each of [DURATION_BUCKETS] (and no
+    /// smaller bucket).
+    counts: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        if let Some(i) = DURATION_BUCKETS.iter().position(|le| seconds <= *le) {
+            self.counts[i] += 1;
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+impl Metrics {
+    /// Adds how the run described by `report` went. `provider` is the cloud
+    /// service provider of the job's bucket, if the job could be found.
+    pub(crate) fn record(
+        &self,
+        report: &JobReport,
+        provider: Option<&CloudServiceProvider>,
+        error: Option<&JobRunnerError>,
+    ) {
+        let provider = provider.map_or("unknown", provider_label);
+        let job = &report.job;
+        let status = report.status.as_str();
+
+        let mut inner = self.inner.lock().expect("metrics lock was poisoned");
+        *inner.jobs.entry((job.clone(), status)).or_default() += 1;
+        inner
+            .job_durations
+            .entry(job.clone())
+            .or_default()
+            .observe(report.duration);
+        for (index, duration) in &report.stats.step_durations {
+            inner
+                .step_durations
+                .entry((job.clone(), *index))
+                .or_default()
+                .observe(*duration);
+        }
+        *inner
+            .objects_downloaded
+            .entry((job.clone(), provider))
+            .or_default() += report.stats.objects_downloaded as u64;
+        *inner
+            .bytes_downloaded
+            .entry((job.clone(), provider))
+            .or_default() += report.stats.bytes_downloaded;
+        *inner
+            .bytes_uploaded
+            .entry((job.clone(), provider))
+            .or_default() += report.stats.bytes_uploaded;
+        if let Some(operation) = error.and_then(bucket_operation) {
+            *inner
+                .bucket_errors
+                .entry((provider, operation))
+                .or_default() += 1;
+        }
+        if report.status != JobStatus::Failed {
+            let finished_at =
+                report.started_at + chrono::Duration::from_std(report.duration).unwrap_or_default();
+            inner
+                .last_success
+                .insert(job.clone(), finished_at.timestamp());
+        }
+    }
+
+    /// Renders every metric in Prometheus' text exposition format.
+    pub fn render(&self) -> String {
+        let inner = self.inner.lock().expect("metrics lock was poisoned");
+        let mut out = String::new();
+
+        write_header(
+            &mut out,
+            "csjr_jobs_total",
+            "counter",
+            "Jobs run, by how they ended.",
+        );
+        for ((job, status), value) in &inner.jobs {
+            write_sample(
+                &mut out,
+                "csjr_jobs_total",
+                &[("job", job), ("status", status)],
+                *value,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "csjr_job_duration_seconds",
+            "histogram",
+            "How long jobs took to run.",
+        );
+        for (job, histogram) in &inner.job_durations {
+            write_histogram(
+                &mut out,
+                "csjr_job_duration_seconds",
+                &[("job", job)],
+                histogram,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "csjr_step_duration_seconds",
+            "histogram",
+            "How long each of a job's steps took to run.",
+        );
+        for ((job, index), histogram) in &inner.step_durations {
+            write_histogram(
+                &mut out,
+                "csjr_step_duration_seconds",
+                &[("job", job), ("step", &index.to_string())],
+                histogram,
+            );
+        }
+
+        for (name, help, values) in [
+            (
+                "csjr_objects_downloaded_total",
+                "Objects downloaded from buckets.",
+                &inner.objects_downloaded,
+            ),
+            (
+                "csjr_downloaded_bytes_total",
+                "Bytes downloaded from buckets.",
+                &inner.bytes_downloaded,
+            ),
+            (
+                "csjr_uploaded_bytes_total",
+                "Bytes uploaded to buckets.",
+                &inner.bytes_uploaded,
+            ),
+        ] {
+            write_header(&mut out, name, "counter", help);
+            for ((job, provider), value) in values {
+                write_sample(
+                    &mut out,
+                    name,
+                    &[("job", job), ("provider", provider)],
+                    *value,
+                );
+            }
+        }
+
+        write_header(
+            &mut out,
+            "csjr_bucket_errors_total",
+            "counter",
+            "Bucket operations that failed a job. Failed operations aren't retried.",
+        );
+        for ((provider, operation), value) in &inner.bucket_errors {
+            write_sample(
+                &mut out,
+                "csjr_bucket_errors_total",
+                &[("provider", provider), ("operation", operation)],
+                *value,
+            );
+        }
+
+        write_header(
+            &mut out,
+            "csjr_last_success_timestamp_seconds",
+            "gauge",
+            "When each job last finished without failing, as a Unix timestamp.",
+        );
+        for (job, timestamp) in &inner.last_success {
+            write_sample(
+                &mut out,
+                "csjr_last_success_timestamp_seconds",
+                &[("job", job)],
+                *timestamp,
+            );
+        }
+        out
+    }
+
+    /// Writes every metric to `path`, for node_exporter's textfile collector
+    /// to pick up. The file is replaced all at once, so the collector never
+    /// reads a half-written file.
+    pub fn write_textfile(&self, path: &Path) -> Result<()> {
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let tmp_path = path.with_extension("prom.tmp");
+            fs::write(&tmp_path, self.render())?;
+            fs::rename(&tmp_path, path)
+        };
+        write().map_err(|err| ReportError {
+            path: path.into(),
+            source: Box::new(err),
+        })
+    }
+}
+
+/// Serves `metrics` at `/metrics` on `address`, until something goes wrong
+/// accepting connections. Binds to `address` before returning the future
+/// that serves requests, so that a port that's already taken is reported
+/// right away.
+pub async fn serve(
+    address: SocketAddr,
+    metrics: Arc<Metrics>,
+) -> Result<impl std::future::Future<Output = Result<()>>> {
+    let listener = TcpListener::bind(address).await?;
+    Ok(async move {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let metrics = Arc::clone(&metrics);
+            tokio::spawn(async move {
+                // A client that hangs up early isn't our problem.
+                let _ = respond(stream, &metrics).await;
+            });
+        }
+    })
+}
+
+async fn respond(stream: tokio::net::TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    let mut stream = BufReader::new(stream);
+    let mut request_line = String::new();
+    stream.read_line(&mut request_line).await?;
+    // Skip the headers; nothing in them matters.
+    let mut header = String::new();
+    while stream.read_line(&mut header).await? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics.render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.get_mut().write_all(response.as_bytes()).await?;
+    stream.get_mut().shutdown().await
+}
+
+fn provider_label(provider: &CloudServiceProvider) -> &'static str {
+    match provider {
+        CloudServiceProvider::GCP => "gcp",
+    }
+}
+
+/// Returns which bucket operation `error` came from, if it came from one.
+fn bucket_operation(error: &JobRunnerError) -> Option<&'static str> {
+    match error {
+        JobRunnerError::BucketCredentialsNotFoundError(_) => Some("authenticate"),
+        JobRunnerError::ListFilesInBucketError { .. } => Some("list"),
+        JobRunnerError::DownloadFromBucketError { .. } => Some("download"),
+        JobRunnerError::UploadToBucketError { .. } => Some("upload"),
+        JobRunnerError::CopyWithinBucketError { .. } => Some("copy"),
+        JobRunnerError::DeleteFromBucketError { .. } => Some("delete"),
+        _ => None,
+    }
+}
+
+// Writing to a String can't fail, so the results of writeln!() are ignored
+// below.
+
+fn write_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn write_sample(out: &mut String, name: &str, labels: &[(&str, &str)], value: impl ToString) {
+    let _ = writeln!(out, "{name}{} {}", format_labels(labels), value.to_string());
+}
+
+fn write_histogram(out: &mut String, name: &str, labels: &[(&str, &str)], histogram: &Histogram) {
+    let mut cumulative = 0;
+    for (le, count) in DURATION_BUCKETS.iter().zip(histogram.counts) {
+        cumulative += count;
+        let le = le.to_string();
+        let labels: Vec<_> = labels
+            .iter()
+            .copied()
+            .chain([("le", le.as_str())])
+            .collect();
+        write_sample(out, &format!("{name}_bucket"), &labels, cumulative);
+    }
+    let labels_with_inf: Vec<_> = labels.iter().copied().chain([("le", "+Inf")]).collect();
+    write_sample(
+        out,
+        &format!("{name}_bucket"),
+        &labels_with_inf,
+        histogram.count,
+    );
+    write_sample(out, &format!("{name}_sum"), labels, histogram.sum);
+    write_sample(out, &format!("{name}_count"), labels, histogram.count);
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod render_tests {
+    use super::Metrics;
+    use crate::{
+        errors::JobRunnerError::{DownloadFromBucketError, StepNonZeroStatusCodeError},
+        report::{JobReport, JobStats},
+        CloudServiceProvider, Result,
+    };
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    fn record(metrics: &Metrics, job: &str, stats: JobStats, result: Result<()>) {
+        let started_at = Utc.with_ymd_and_hms(2022, 4, 18, 2, 0, 0).unwrap();
+        let report = JobReport::new(job, started_at, Duration::from_secs(2), stats, &result);
+        metrics.record(
+            &report,
+            Some(&CloudServiceProvider::GCP),
+            result.as_ref().err(),
+        );
+    }
+
+    #[test]
+    fn step_label_is_index_in_job_not_in_run() {
+        let metrics = Metrics::default();
+        let stats = JobStats {
+            step_durations: vec![(2, Duration::from_millis(700))],
+            ..Default::default()
+        };
+        record(&metrics, "resize", stats, Ok(()));
+        let rendered = metrics.render();
+
+        assert!(rendered
+            .lines()
+            .any(|l| l == r#"csjr_step_duration_seconds_count{job="resize",step="2"} 1"#));
+        assert!(!rendered.contains(r#"step="0""#));
+    }
+
+    #[test]
+    fn counts_jobs_bytes_and_bucket_errors() {
+        let metrics = Metrics::default();
+        let stats = JobStats {
+            objects_downloaded: 2,
+            bytes_downloaded: 300,
+            bytes_uploaded: 100,
+            step_durations: vec![(0, Duration::from_millis(700))],
+            ..Default::default()
+        };
+        record(&metrics, "resize", stats.clone(), Ok(()));
+        record(&metrics, "resize", stats, Ok(()));
+        record(
+            &metrics,
+            "resize",
+            JobStats::default(),
+            Err(DownloadFromBucketError {
+                source: "timed out".into(),
+            }),
+        );
+        record(
+            &metrics,
+            "say \"hi\"",
+            JobStats::default(),
+            Err(StepNonZeroStatusCodeError {
+                step: "false".into(),
+                code: Some(1),
+            }),
+        );
+        let rendered = metrics.render();
+
+        for line in [
+            r#"csjr_jobs_total{job="resize",status="failed"} 1"#,
+            r#"csjr_jobs_total{job="resize",status="succeeded"} 2"#,
+            r#"csjr_jobs_total{job="say \"hi\"",status="failed"} 1"#,
+            r#"csjr_downloaded_bytes_total{job="resize",provider="gcp"} 600"#,
+            r#"csjr_uploaded_bytes_total{job="resize",provider="gcp"} 200"#,
+            r#"csjr_bucket_errors_total{provider="gcp",operation="download"} 1"#,
+            r#"csjr_step_duration_seconds_bucket{job="resize",step="0",le="0.5"} 0"#,
+            r#"csjr_step_duration_seconds_bucket{job="resize",step="0",le="1"} 2"#,
+            r#"csjr_step_duration_seconds_bucket{job="resize",step="0",le="+Inf"} 2"#,
+            r#"csjr_step_duration_seconds_count{job="resize",step="0"} 2"#,
+            r#"csjr_job_duration_seconds_count{job="resize"} 3"#,
+            r#"csjr_last_success_timestamp_seconds{job="resize"} 1650247202"#,
+        ] {
+            assert!(
+                rendered.lines().any(|l| l == line),
+                "{line:?} not in:\n{rendered}"
+            );
+        }
+        assert!(!rendered.contains(r#"csjr_last_success_timestamp_seconds{job="say"#));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::{
+    bucket::{keys, Object},
+    errors::JobRunnerError::InvalidNotificationError,
+    pretty_print, Job, JobRunner, Result,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, Lines};
+
+/// A message pulled from a [NotificationSource].
+#[derive(Debug)]
+pub struct Message {
+    /// The message's contents. Expected to be a GCS Pub/Sub notification, or
+    /// an S3 event notification, as JSON.
+    pub body: String,
+    /// Whatever the source needs to acknowledge this message, if anything.
+    pub ack_id: Option<String>,
+}
+
+/// Somewhere that notifications about objects being created in a cloud
+/// storage bucket come from, like a Pub/Sub subscription or an SQS queue.
+#[async_trait]
+pub trait NotificationSource: Send {
+    /// Waits for the next message. Returns `None` once there are no more
+    /// messages to wait for.
+    async fn receive(&mut self) -> Result<Option<Message>>;
+
+    /// Tells the source that `message` has been handled, and shouldn't be
+    /// delivered again. Does nothing by default.
+    async fn acknowledge(&mut self, _message: &Message) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A [NotificationSource] that reads one message per line from a file, or
+/// from stdin. Useful for trying things out without a real message queue.
+pub struct JsonLinesSource<R> {
+    lines: Lines<R>,
+}
+
+impl<R: AsyncBufRead + Unpin> JsonLinesSource<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+        }
+    }
+}
+
+#[async_trait]
+impl<R: AsyncBufRead + Unpin + Send> NotificationSource for JsonLinesSource<R> {
+    async fn receive(&mut self) -> Result<Option<Message>> {
+        while let Some(line) = self.lines.next_line().await? {
+            if !line.trim().is_empty() {
+                return Ok(Some(Message {
+                    body: line,
+                    ack_id: None,
+                }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A notification that an object was created (or overwritten) in a bucket.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Notification {
+    pub bucket_name: String,
+    pub object: Object,
+}
+
+/// Parses the notifications in a message's `body`. Understands:
+///
+/// - GCS Pub/Sub notifications, either as the object resource in the message's
+///   payload, or as a push subscription's request body (where the message's
+///   attributes describe the object)
+/// - S3 event notifications, as delivered to SQS
+///
+/// Notifications about anything other than an object being created are left
+/// out.
+pub fn parse_notifications(body: &str) -> Result<Vec<Notification>> {
+    let invalid = |reason: &str| InvalidNotificationError {
+        body: body.into(),
+        source: reason.into(),
+    };
+    let value: Value = serde_json::from_str(body).map_err(|err| InvalidNotificationError {
+        body: body.into(),
+        source: Box::new(err),
+    })?;
+
+    // S3 event notification.
+    if let Some(records) = value.get("Records").and_then(Value::as_array) {
+        let mut notifications = Vec::new();
+        for record in records {
+            let event_name = record["eventName"].as_str().unwrap_or_default();
+            if !event_name.starts_with("ObjectCreated:") {
+                continue;
+            }
+            let bucket_name = record["s3"]["bucket"]["name"]
+                .as_str()
+                .ok_or_else(|| invalid("S3 record is missing the bucket's name"))?;
+            let key = record["s3"]["object"]["key"]
+                .as_str()
+                .ok_or_else(|| invalid("S3 record is missing the object's key"))?;
+            notifications.push(Notification {
+                bucket_name: bucket_name.into(),
+                object: Object {
+                    name: decode_s3_key(key),
+                    generation: s3_generation(&record["s3"]["object"]),
+                },
+            });
+        }
+        return Ok(notifications);
+    }
+
+    // GCS Pub/Sub push subscription request body.
+    if let Some(attributes) = value.get("message").and_then(|m| m.get("attributes")) {
+        let event_type = attributes["eventType"].as_str().unwrap_or_default();
+        if event_type != "OBJECT_FINALIZE" {
+            return Ok(Vec::new());
+        }
+        let bucket_name = attributes["bucketId"]
+            .as_str()
+            .ok_or_else(|| invalid("Pub/Sub message is missing the \"bucketId\" attribute"))?;
+        let name = attributes["objectId"]
+            .as_str()
+            .ok_or_else(|| invalid("Pub/Sub message is missing the \"objectId\" attribute"))?;
+        let generation = attributes["objectGeneration"]
+            .as_str()
+            .and_then(|g| g.parse().ok())
+            .ok_or_else(|| invalid("Pub/Sub message has an invalid \"objectGeneration\""))?;
+        return Ok(vec![Notification {
+            bucket_name: bucket_name.into(),
+            object: Object {
+                name: name.into(),
+                generation,
+            },
+        }]);
+    }
+
+    // GCS object resource.
+    if value["kind"] == "storage#object" {
+        let bucket_name = value["bucket"]
+            .as_str()
+            .ok_or_else(|| invalid("object resource is missing \"bucket\""))?;
+        let name = value["name"]
+            .as_str()
+            .ok_or_else(|| invalid("object resource is missing \"name\""))?;
+        let generation = value["generation"]
+            .as_str()
+            .and_then(|g| g.parse().ok())
+            .ok_or_else(|| invalid("object resource has an invalid \"generation\""))?;
+        return Ok(vec![Notification {
+            bucket_name: bucket_name.into(),
+            object: Object {
+                name: name.into(),
+                generation,
+            },
+        }]);
+    }
+
+    Err(invalid("not a GCS or S3 notification"))
+}
+
+/// S3 objects don't have generations, so makes one up from whatever in an S3
+/// record's `object` changes each time the object is written: its
+/// `sequencer`, its `versionId` (if the bucket is versioned), or else its
+/// `eTag`. That way, an object that's overwritten gets a new generation, and
+/// isn't skipped because of the job's ledger. Returns 0 if the record has
+/// none of those.
+///
+/// The generation is an FNV-1a hash, rather than one from
+/// [std::collections::hash_map::DefaultHasher], so that it's the same from one
+/// version of Rust to the next, since generations are stored in ledgers.
+fn s3_generation(object: &Value) -> i64 {
+    let Some(id) = ["sequencer", "versionId", "eTag"]
+        .iter()
+        .find_map(|field| object[field].as_str())
+    else {
+        return 0;
+    };
+    let hash = id.bytes().fold(0xcbf29ce484222325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    });
+    hash as i64
+}
+
+/// S3 URL-encodes object keys in event notifications, with spaces encoded as
+/// `+`.
+fn decode_s3_key(key: &str) -> String {
+    let bytes = key.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl Job {
+    /// Returns true if `notification` is about one of this [Job]'s inputs.
+    fn is_input(&self, notification: &Notification) -> bool {
+        notification.bucket_name == self.bucket_name
+            && keys::is_inside(&notification.object.name, &self.path_to_remote_inputs)
+    }
+}
+
+impl<'a> JobRunner<'a> {
+    /// Receives messages from `source` until it runs out, and runs the jobs
+    /// whose inputs the notifications in those messages are about, on just
+    /// the objects they're about.
+    ///
+    /// A message is acknowledged once every job it triggered succeeds. Jobs
+    /// that fail are reported, and their message isn't acknowledged, so that
+    /// the source can deliver it again. Messages that can't be parsed are
+    /// reported and acknowledged, since they'll never succeed.
+    pub async fn listen<N: NotificationSource>(&mut self, source: &mut N) -> Result<()> {
+        let mut job_names: Vec<&String> = self.config.jobs.keys().collect();
+        job_names.sort();
+
+        while let Some(message) = source.receive().await? {
+            let notifications = match parse_notifications(&message.body) {
+                Ok(notifications) => notifications,
+                Err(e) => {
+                    pretty_print::error(&e)?;
+                    source.acknowledge(&message).await?;
+                    continue;
+                }
+            };
+
+            let mut succeeded = true;
+            for job_name in &job_names {
+                let job = &self.config.jobs[*job_name];
+                let objects: Vec<Object> = notifications
+                    .iter()
+                    .filter(|n| job.is_input(n))
+                    .map(|n| n.object.clone())
+                    .collect();
+                if objects.is_empty() {
+                    continue;
+                }
+                pretty_print::status(
+                    "Notified",
+                    &format!("of {} new object(s) for {job_name}", objects.len()),
+                    false,
+                )?;
+                if let Err(e) = self
+                    .run_job_without_checkpoint(job_name, Some(&objects))
+                    .await
+                {
+                    pretty_print::error(&e)?;
+                    succeeded = false;
+                }
+            }
+            if succeeded {
+                source.acknowledge(&message).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod parse_notifications_tests {
+    use super::{parse_notifications, Notification};
+    use crate::bucket::Object;
+
+    fn notification(bucket_name: &str, name: &str, generation: i64) -> Notification {
+        Notification {
+            bucket_name: bucket_name.into(),
+            object: Object {
+                name: name.into(),
+                generation,
+            },
+        }
+    }
+
+    #[test]
+    fn gcs_object_resource() {
+        let body =
+            r#"{"kind":"storage#object","bucket":"foo","name":"in/a.png","generation":"42"}"#;
+        assert_eq!(
+            parse_notifications(body).unwrap(),
+            vec![notification("foo", "in/a.png", 42)]
+        );
+    }
+
+    #[test]
+    fn gcs_push_subscription() {
+        let finalize = r#"{"message":{"attributes":{"eventType":"OBJECT_FINALIZE","bucketId":"foo","objectId":"in/a.png","objectGeneration":"7"},"data":""}}"#;
+        assert_eq!(
+            parse_notifications(finalize).unwrap(),
+            vec![notification("foo", "in/a.png", 7)]
+        );
+        let delete = r#"{"message":{"attributes":{"eventType":"OBJECT_DELETE","bucketId":"foo","objectId":"in/a.png","objectGeneration":"7"}}}"#;
+        assert!(parse_notifications(delete).unwrap().is_empty());
+    }
+
+    #[test]
+    fn s3_event() {
+        let body = r#"{"Records":[
+            {"eventName":"ObjectCreated:Put","s3":{"bucket":{"name":"foo"},"object":{"key":"in/my+photo%281%29.png"}}},
+            {"eventName":"ObjectRemoved:Delete","s3":{"bucket":{"name":"foo"},"object":{"key":"in/b.png"}}}
+        ]}"#;
+        assert_eq!(
+            parse_notifications(body).unwrap(),
+            vec![notification("foo", "in/my photo(1).png", 0)]
+        );
+    }
+
+    #[test]
+    fn s3_event_generation_changes_when_object_is_overwritten() {
+        let body = r#"{"Records":[
+            {"eventName":"ObjectCreated:Put","s3":{"bucket":{"name":"foo"},"object":{"key":"in/a.png","eTag":"abc","sequencer":"0055AED6DCD90281E5"}}},
+            {"eventName":"ObjectCreated:Put","s3":{"bucket":{"name":"foo"},"object":{"key":"in/a.png","eTag":"abc","sequencer":"0055AED6DCD90281E6"}}}
+        ]}"#;
+        let notifications = parse_notifications(body).unwrap();
+        assert_ne!(notifications[0].object.generation, 0);
+        assert_ne!(
+            notifications[0].object.generation,
+            notifications[1].object.generation
+        );
+        assert_eq!(
+            notifications[0],
+            parse_notifications(body).unwrap().remove(0)
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(parse_notifications("not json").is_err());
+        assert!(parse_notifications(r#"{"foo":"bar"}"#).is_err());
+        assert!(parse_notifications(r#"{"kind":"storage#object","bucket":"foo"}"#).is_err());
+    }
+}
@@ -1,31 +1,664 @@
 use std::{
+    error::Error,
+    ffi::OsStr,
     fmt,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
 };
 
+use crate::report::ErrorReport;
+use chrono::Utc;
+use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
 use termcolor::{
     Color::{Green, Red},
     ColorChoice, ColorSpec, StandardStream, WriteColor,
 };
 
-pub fn status(prefix: &str, msg: &dyn fmt::Display, is_indented: bool) -> io::Result<()> {
-    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
-    stderr.reset()?; // Just in case.
-    stderr.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Green)))?;
-    if is_indented {
-        write!(stderr, "    {prefix}")?;
+/// How status messages, events, and errors are written to stderr.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Colored text meant for humans. Most events aren't shown.
+    #[default]
+    Human,
+    /// One JSON object per line, for every event (except when quiet).
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "\"{s}\" isn't a log format (expected human or json)"
+            )),
+        }
+    }
+}
+
+/// How much gets written to stderr.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Only errors.
+    Quiet,
+    /// Status messages, as well as errors.
+    #[default]
+    Normal,
+    /// Also each object that's transferred, and how long each step took.
+    Verbose,
+    /// Also the details of how csjr goes about things, like the exact command
+    /// line each step is run with.
+    Debug,
+}
+
+impl Verbosity {
+    /// Returns the [Verbosity] that `-q/--quiet` and `num_verbose_flags`
+    /// repetitions of `-v` ask for.
+    pub fn from_flags(quiet: bool, num_verbose_flags: u64) -> Self {
+        match (quiet, num_verbose_flags) {
+            (true, _) => Self::Quiet,
+            (false, 0) => Self::Normal,
+            (false, 1) => Self::Verbose,
+            (false, _) => Self::Debug,
+        }
+    }
+}
+
+/// Whether text written for humans is colored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Colored if stderr is a terminal, unless the `NO_COLOR` environment
+    /// variable is set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "\"{s}\" isn't a color mode (expected auto, always, or never)"
+            )),
+        }
+    }
+}
+
+/// Everything that changes how this module writes things.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Settings {
+    pub log_format: LogFormat,
+    pub verbosity: Verbosity,
+    pub color: ColorMode,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+
+/// Sets how everything in this module writes things. Until it's called,
+/// [Settings::default] is used.
+///
+/// # Errors
+///
+/// Can only be set once. Later calls change nothing, and hand back the
+/// `settings` they were passed.
+pub fn configure(settings: Settings) -> Result<(), Settings> {
+    SETTINGS.set(settings)
+}
+
+fn settings() -> Settings {
+    SETTINGS.get().copied().unwrap_or_default()
+}
+
+pub fn log_format() -> LogFormat {
+    settings().log_format
+}
+
+/// Returns true if messages meant for `verbosity` (or less) are written.
+pub fn is_shown_at(verbosity: Verbosity) -> bool {
+    settings().verbosity >= verbosity
+}
+
+fn color_choice() -> ColorChoice {
+    let no_color = std::env::var_os("NO_COLOR");
+    if is_colored(
+        settings().color,
+        io::stderr().is_terminal(),
+        no_color.as_deref(),
+    ) {
+        ColorChoice::Always
     } else {
-        write!(stderr, "{prefix}")?;
+        ColorChoice::Never
+    }
+}
+
+/// Returns true if text written for humans is colored in `mode`, given
+/// whether stderr `is_terminal`, and the value of the `NO_COLOR` environment
+/// variable, which only counts if it isn't empty.
+fn is_colored(mode: ColorMode, is_terminal: bool, no_color: Option<&OsStr>) -> bool {
+    match mode {
+        ColorMode::Always => true,
+        ColorMode::Never => false,
+        ColorMode::Auto => is_terminal && no_color.is_none_or(OsStr::is_empty),
+    }
+}
+
+/// Returns true if progress bars and spinners are drawn, which they only are
+/// for people watching csjr in a terminal.
+pub fn is_progress_shown() -> bool {
+    log_format() == LogFormat::Human && is_shown_at(Verbosity::Normal) && io::stderr().is_terminal()
+}
+
+/// The progress bar or spinner that's currently drawn, if any. Anything else
+/// written to the terminal has to clear it first, and redraw it afterwards.
+static ACTIVE_PROGRESS: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Runs `write`, with the progress bar or spinner that's currently drawn (if
+/// any) hidden while it does.
+fn with_progress_hidden<T>(write: impl FnOnce() -> T) -> T {
+    let active = ACTIVE_PROGRESS
+        .lock()
+        .expect("progress lock was poisoned")
+        .clone();
+    match active {
+        Some(bar) => bar.suspend(write),
+        None => write(),
+    }
+}
+
+/// A progress bar or spinner, drawn on stderr until it's finished (or
+/// dropped). Does nothing if [is_progress_shown] is false.
+pub struct Progress {
+    bar: Option<ProgressBar>,
+    started_at: Instant,
+    num_bytes: u64,
+}
+
+impl Progress {
+    fn start(bar: ProgressBar) -> Self {
+        bar.enable_steady_tick(Duration::from_millis(100));
+        *ACTIVE_PROGRESS.lock().expect("progress lock was poisoned") = Some(bar.clone());
+        Self {
+            bar: Some(bar),
+            started_at: Instant::now(),
+            num_bytes: 0,
+        }
+    }
+
+    fn hidden() -> Self {
+        Self {
+            bar: None,
+            started_at: Instant::now(),
+            num_bytes: 0,
+        }
+    }
+
+    /// Marks one more object as transferred, along with its `num_bytes`.
+    pub fn advance(&mut self, num_bytes: u64) {
+        self.num_bytes += num_bytes;
+        if let Some(bar) = &self.bar {
+            let seconds = self.started_at.elapsed().as_secs_f64().max(0.001);
+            let throughput = (self.num_bytes as f64 / seconds) as u64;
+            bar.set_message(format!(
+                "{}, {}/s",
+                HumanBytes(self.num_bytes),
+                HumanBytes(throughput)
+            ));
+            bar.inc(1);
+        }
+    }
+
+    /// Stops drawing this progress bar or spinner, and clears it. Dropping it
+    /// does the same, but finishing it says so where it happens.
+    pub fn finish(mut self) {
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish_and_clear();
+            *ACTIVE_PROGRESS.lock().expect("progress lock was poisoned") = None;
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Starts drawing a progress bar for transferring `num_objects` objects, which
+/// shows how many are done, how many bytes that is, the throughput, and how
+/// long until it's done. `verb` says which way they're going, like
+/// "Downloading".
+pub fn transfer_progress(verb: &str, num_objects: usize) -> Progress {
+    if !is_progress_shown() {
+        return Progress::hidden();
+    }
+    let style = ProgressStyle::with_template(&format!(
+        "    {{spinner{}}} {verb} [{{bar:30}}] {{pos}}/{{len}} object(s), {{msg}}, ETA {{eta}}",
+        spinner_color()
+    ))
+    .expect("progress bar template is valid")
+    .progress_chars("=> ");
+    let bar = ProgressBar::new(num_objects as u64).with_style(style);
+    bar.set_message(format!("{}", HumanBytes(0)));
+    Progress::start(bar)
+}
+
+/// Returns the style that progress bars' and spinners' spinners are drawn in.
+fn spinner_color() -> &'static str {
+    match color_choice() {
+        ColorChoice::Never => "",
+        _ => ":.green",
+    }
+}
+
+/// Starts drawing a spinner that shows how long `step` has been running for.
+pub fn step_progress(step: &str) -> Progress {
+    if !is_progress_shown() {
+        return Progress::hidden();
+    }
+    let style = ProgressStyle::with_template(&format!(
+        "    {{spinner{}}} `{{msg}}` ({{elapsed}})",
+        spinner_color()
+    ))
+    .expect("spinner template is valid");
+    let bar = ProgressBar::new_spinner()
+        .with_style(style)
+        .with_message(step.to_string());
+    Progress::start(bar)
+}
+
+/// Something notable that happened while running a job.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    JobStarted {
+        job: &'a str,
+    },
+    JobFinished {
+        job: &'a str,
+        succeeded: bool,
+        #[serde(rename = "duration_ms", serialize_with = "serialize_as_millis")]
+        duration: Duration,
+    },
+    DownloadStarted {
+        object: &'a str,
+    },
+    DownloadFinished {
+        object: &'a str,
+        #[serde(rename = "duration_ms", serialize_with = "serialize_as_millis")]
+        duration: Duration,
+    },
+    StepStarted {
+        index: usize,
+        step: &'a str,
+    },
+    StepOutput {
+        stream: &'a str,
+        line: &'a str,
+    },
+    StepFinished {
+        index: usize,
+        step: &'a str,
+        exit_code: Option<i32>,
+        #[serde(rename = "duration_ms", serialize_with = "serialize_as_millis")]
+        duration: Duration,
+    },
+    UploadStarted {
+        from: &'a str,
+        to: &'a str,
+    },
+    UploadFinished {
+        from: &'a str,
+        to: &'a str,
+        #[serde(rename = "duration_ms", serialize_with = "serialize_as_millis")]
+        duration: Duration,
+    },
+}
+
+pub(crate) fn serialize_as_millis<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Returns the [Verbosity] that has to be configured for `event` to be written
+/// as JSON. A step's output is always written, the same way that it still
+/// shows up in a terminal when csjr is quiet.
+fn json_verbosity(event: &Event) -> Verbosity {
+    match event {
+        Event::StepOutput { .. } => Verbosity::Quiet,
+        _ => Verbosity::Normal,
+    }
+}
+
+/// Writes an [Event]. Human-readable output only shows the events that people
+/// watching a job care about, while JSON output shows every event, unless
+/// csjr is quiet.
+pub fn event(event: &Event) -> io::Result<()> {
+    match log_format() {
+        LogFormat::Human => match event {
+            Event::StepStarted { step, .. } => status("Running", &format!("`{step}`"), true),
+            Event::UploadStarted { from, to } => {
+                status("Uploading", &format!("\"{from}\" to \"{to}\""), true)
+            }
+            Event::DownloadFinished { object, duration } => status_at(
+                Verbosity::Verbose,
+                "Downloaded",
+                &format!("\"{object}\" in {}", HumanDuration(*duration)),
+                true,
+            ),
+            Event::StepFinished {
+                step,
+                exit_code,
+                duration,
+                ..
+            } => status_at(
+                Verbosity::Verbose,
+                "Finished",
+                &match exit_code {
+                    Some(code) => format!(
+                        "`{step}` in {} with exit code {code}",
+                        HumanDuration(*duration)
+                    ),
+                    None => format!("`{step}` in {}", HumanDuration(*duration)),
+                },
+                true,
+            ),
+            // Steps' output is only captured (instead of going straight to the
+            // terminal) when there's a spinner in the way.
+            Event::StepOutput { stream, line } => with_progress_hidden(|| match *stream {
+                "stderr" => writeln!(io::stderr(), "{line}"),
+                _ => writeln!(io::stdout(), "{line}"),
+            }),
+            _ => Ok(()),
+        },
+        LogFormat::Json if is_shown_at(json_verbosity(event)) => {
+            write_json_line(serde_json::to_value(event)?)
+        }
+        LogFormat::Json => Ok(()),
+    }
+}
+
+pub fn status(prefix: &str, msg: &dyn fmt::Display, is_indented: bool) -> io::Result<()> {
+    status_at(Verbosity::Normal, prefix, msg, is_indented)
+}
+
+/// Like [status], but only writes anything if the [Verbosity] that's been
+/// configured is at least `verbosity`.
+pub fn status_at(
+    verbosity: Verbosity,
+    prefix: &str,
+    msg: &dyn fmt::Display,
+    is_indented: bool,
+) -> io::Result<()> {
+    if !is_shown_at(verbosity) {
+        return Ok(());
+    }
+    if log_format() == LogFormat::Json {
+        return write_json_line(json!({
+            "event": "status",
+            "status": prefix,
+            "message": msg.to_string(),
+        }));
+    }
+
+    with_progress_hidden(|| {
+        let mut stderr = StandardStream::stderr(color_choice());
+        stderr.reset()?; // Just in case.
+        stderr.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Green)))?;
+        if is_indented {
+            write!(stderr, "    {prefix}")?;
+        } else {
+            write!(stderr, "{prefix}")?;
+        }
+        stderr.reset()?;
+        writeln!(stderr, " {msg}")
+    })
+}
+
+pub fn error(err: &dyn Error) -> io::Result<()> {
+    if log_format() == LogFormat::Json {
+        let error = ErrorReport::new(err);
+        return write_json_line(json!({
+            "event": "error",
+            "message": error.message,
+            "sources": error.sources,
+        }));
+    }
+
+    with_progress_hidden(|| {
+        let mut stderr = StandardStream::stderr(color_choice());
+        stderr.reset()?; // Just in case.
+        stderr.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Red)))?;
+        write!(stderr, "error: ")?;
+        stderr.reset()?;
+        writeln!(stderr, "{err}")
+    })
+}
+
+/// Writes `value` to stderr on a line of its own, with the current time added
+/// to it.
+fn write_json_line(mut value: Value) -> io::Result<()> {
+    if let Value::Object(fields) = &mut value {
+        fields.insert("time".into(), Utc::now().to_rfc3339().into());
+    }
+    let mut stderr = io::stderr().lock();
+    serde_json::to_writer(&mut stderr, &value)?;
+    writeln!(stderr)
+}
+
+#[cfg(test)]
+mod configure_tests {
+    use super::{configure, settings, ColorMode, LogFormat, Settings, Verbosity};
+
+    #[test]
+    fn settings_can_only_be_configured_once() {
+        // The only test that configures anything, so that every other test
+        // sees the defaults.
+        configure(Settings::default()).unwrap();
+        let later = Settings {
+            log_format: LogFormat::Json,
+            verbosity: Verbosity::Quiet,
+            color: ColorMode::Never,
+        };
+
+        let result = configure(later);
+
+        assert!(matches!(result, Err(s) if s.log_format == LogFormat::Json));
+        assert_eq!(settings().log_format, LogFormat::Human);
+        assert_eq!(settings().verbosity, Verbosity::Normal);
+    }
+}
+
+#[cfg(test)]
+mod json_verbosity_tests {
+    use super::{json_verbosity, Event, Verbosity};
+    use std::time::Duration;
+
+    #[test]
+    fn events_are_quiet_except_step_output() {
+        let quiet = Verbosity::Quiet;
+        assert!(json_verbosity(&Event::JobStarted { job: "resize" }) > quiet);
+        assert!(
+            json_verbosity(&Event::JobFinished {
+                job: "resize",
+                succeeded: false,
+                duration: Duration::ZERO,
+            }) > quiet
+        );
+        assert_eq!(
+            json_verbosity(&Event::StepOutput {
+                stream: "stdout",
+                line: "hi",
+            }),
+            quiet
+        );
+    }
+}
+
+#[cfg(test)]
+mod event_tests {
+    use super::{Event, Verbosity};
+    use serde_json::json;
+    use std::time::Duration;
+
+    #[test]
+    fn events_are_tagged_with_their_name() {
+        assert_eq!(
+            serde_json::to_value(Event::JobStarted { job: "resize" }).unwrap(),
+            json!({"event": "job_started", "job": "resize"})
+        );
+        assert_eq!(
+            serde_json::to_value(Event::StepOutput {
+                stream: "stderr",
+                line: "warning: low disk space",
+            })
+            .unwrap(),
+            json!({"event": "step_output", "stream": "stderr", "line": "warning: low disk space"})
+        );
+    }
+
+    #[test]
+    fn durations_are_in_millis() {
+        assert_eq!(
+            serde_json::to_value(Event::JobFinished {
+                job: "resize",
+                succeeded: true,
+                duration: Duration::from_millis(1500),
+            })
+            .unwrap(),
+            json!({"event": "job_finished", "job": "resize", "succeeded": true, "duration_ms": 1500})
+        );
+        assert_eq!(
+            serde_json::to_value(Event::UploadFinished {
+                from: "out",
+                to: "results",
+                duration: Duration::from_secs(2),
+            })
+            .unwrap(),
+            json!({"event": "upload_finished", "from": "out", "to": "results", "duration_ms": 2000})
+        );
+    }
+
+    #[test]
+    fn step_terminated_by_signal_has_null_exit_code() {
+        assert_eq!(
+            serde_json::to_value(Event::StepFinished {
+                index: 1,
+                step: "sleep 60",
+                exit_code: None,
+                duration: Duration::from_millis(20),
+            })
+            .unwrap(),
+            json!({
+                "event": "step_finished",
+                "index": 1,
+                "step": "sleep 60",
+                "exit_code": null,
+                "duration_ms": 20,
+            })
+        );
+    }
+
+    #[test]
+    fn quiet_wins_over_verbose_flags() {
+        assert_eq!(Verbosity::from_flags(true, 2), Verbosity::Quiet);
+        assert_eq!(Verbosity::from_flags(false, 0), Verbosity::Normal);
+        assert_eq!(Verbosity::from_flags(false, 1), Verbosity::Verbose);
+        assert_eq!(Verbosity::from_flags(false, 3), Verbosity::Debug);
+    }
+
+    #[test]
+    fn more_verbose_shows_everything_less_verbose_does() {
+        assert!(Verbosity::Quiet < Verbosity::Normal);
+        assert!(Verbosity::Normal < Verbosity::Verbose);
+        assert!(Verbosity::Verbose < Verbosity::Debug);
     }
-    stderr.reset()?;
-    writeln!(stderr, " {msg}")
 }
 
-pub fn error(msg: &dyn fmt::Display) -> io::Result<()> {
-    let mut stderr = StandardStream::stderr(ColorChoice::Auto);
-    stderr.reset()?; // Just in case.
-    stderr.set_color(ColorSpec::new().set_bold(true).set_fg(Some(Red)))?;
-    write!(stderr, "error: ")?;
-    stderr.reset()?;
-    writeln!(stderr, "{msg}")
+#[cfg(test)]
+mod color_tests {
+    use super::{is_colored, ColorMode};
+    use std::ffi::OsStr;
+
+    #[test]
+    fn auto_is_colored_in_terminal() {
+        assert!(is_colored(ColorMode::Auto, true, None));
+        assert!(!is_colored(ColorMode::Auto, false, None));
+    }
+
+    #[test]
+    fn auto_respects_no_color_unless_empty() {
+        assert!(!is_colored(ColorMode::Auto, true, Some(OsStr::new("1"))));
+        assert!(is_colored(ColorMode::Auto, true, Some(OsStr::new(""))));
+    }
+
+    #[test]
+    fn always_and_never_ignore_terminal_and_no_color() {
+        assert!(is_colored(ColorMode::Always, false, Some(OsStr::new("1"))));
+        assert!(!is_colored(ColorMode::Never, true, None));
+    }
+
+    #[test]
+    fn color_mode_is_parsed() {
+        assert_eq!("auto".parse(), Ok(ColorMode::Auto));
+        assert_eq!("never".parse(), Ok(ColorMode::Never));
+        assert!("sometimes".parse::<ColorMode>().is_err());
+    }
+}
+
+#[cfg(test)]
+mod progress_tests {
+    use super::Progress;
+    use indicatif::ProgressBar;
+
+    #[test]
+    fn hidden_progress_counts_bytes_without_drawing() {
+        let mut progress = Progress::hidden();
+
+        progress.advance(10);
+        progress.advance(5);
+
+        assert_eq!(progress.num_bytes, 15);
+        assert!(progress.bar.is_none());
+    }
+
+    #[test]
+    fn advancing_moves_bar_and_shows_bytes() {
+        let bar = ProgressBar::hidden();
+        bar.set_length(2);
+        let mut progress = Progress::start(bar.clone());
+
+        progress.advance(2048);
+
+        assert_eq!(bar.position(), 1);
+        assert!(bar.message().starts_with("2.00 KiB, "));
+        progress.finish();
+    }
+
+    #[test]
+    fn finishing_clears_bar() {
+        let bar = ProgressBar::hidden();
+        let progress = Progress::start(bar.clone());
+
+        progress.finish();
+
+        assert!(bar.is_finished());
+    }
 }
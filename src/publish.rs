@@ -0,0 +1,142 @@
+use crate::{
+    bucket::{keys, Bucket},
+    errors::JobRunnerError::InvalidPathError,
+    pretty_print::{self, Verbosity},
+    versioning, Job, PathKeyInConfig, Result,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::{io, path::Path};
+use tracing::instrument;
+
+/// Where in the bucket outputs are uploaded to before they're published.
+const STAGING_PREFIX: &str = ".csjr-staging";
+
+/// Name of the marker object that's written to `path_to_remote_outputs` once
+/// all of a job's outputs have been published.
+const SUCCESS_MARKER: &str = "_SUCCESS";
+
+/// Format of the timestamps that each run's staging prefix is named after.
+const STAGING_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How old a staging prefix has to be before it's considered abandoned by a
+/// run that died partway through.
+const STALE_STAGING_AFTER_HOURS: i64 = 24;
+
+impl Job {
+    /// Uploads this [Job]'s outputs to a staging prefix, copies them into
+    /// `path_to_remote_outputs` once they've all been uploaded, and finally
+    /// writes a `_SUCCESS` marker there. Consumers that wait for the marker
+    /// never see half-uploaded results.
+    ///
+    /// Also deletes any staging prefixes for this job's outputs that were
+    /// abandoned more than a day ago. Staging prefixes live under the part of
+    /// `unscoped_path_to_remote_outputs` that comes before its first run
+    /// identifier, so that they're found by later runs, whose outputs go
+    /// somewhere else. Returns the number of objects published, and the number
+    /// of bytes uploaded.
+    #[instrument(skip_all, fields(remote_outputs = %self.path_to_remote_outputs.display()))]
+    pub(crate) async fn publish_outputs<B: Bucket>(
+        &self,
+        bucket: &B,
+        unscoped_path_to_remote_outputs: &Path,
+    ) -> Result<(usize, u64)> {
+        // Object names don't start with `/`, and joining an absolute path
+        // would replace the staging prefix entirely.
+        let path_to_remote_outputs = keys::normalize(&self.path_to_remote_outputs)
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+        let unscoped_prefix = keys::normalize(&versioning::unscoped_prefix(
+            unscoped_path_to_remote_outputs,
+        ))
+        .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+        let staging_root = Path::new(STAGING_PREFIX).join(unscoped_prefix);
+        let now = Utc::now();
+        delete_stale_staging_prefixes(bucket, &staging_root, now).await?;
+
+        let staging = staging_root.join(now.format(STAGING_TIMESTAMP_FORMAT).to_string());
+        let bytes_uploaded = bucket
+            .upload_outputs(&self.path_to_local_outputs, &staging)
+            .await?;
+
+        // Don't let consumers mistake the previous run's marker for this run's.
+        let marker = path_to_remote_outputs
+            .join(SUCCESS_MARKER)
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?
+            .to_string();
+        let existing_markers = bucket.list_objects(&marker).await?;
+        if existing_markers.iter().any(|o| o.name == marker) {
+            bucket.delete_object(&marker).await?;
+        }
+
+        let staged_objects = bucket
+            .list_objects(
+                staging
+                    .to_str()
+                    .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?,
+            )
+            .await?;
+        pretty_print::status(
+            "Publishing",
+            &format!("{} object(s)", staged_objects.len()),
+            true,
+        )?;
+        for object in &staged_objects {
+            let relative_path = Path::new(&object.name)
+                .strip_prefix(&staging)
+                .map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "staged object \"{}\" isn't inside {:?}",
+                            object.name, staging
+                        ),
+                    )
+                })?;
+            let destination = path_to_remote_outputs.join(relative_path);
+            let destination = destination
+                .to_str()
+                .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+            pretty_print::status_at(
+                Verbosity::Debug,
+                "Publishing",
+                &format!("\"{}\" to \"{destination}\"", object.name),
+                true,
+            )?;
+            bucket.copy_object(&object.name, destination).await?;
+            bucket.delete_object(&object.name).await?;
+        }
+        bucket
+            .write_object(&marker, Vec::new(), "text/plain")
+            .await?;
+        Ok((staged_objects.len(), bytes_uploaded))
+    }
+}
+
+/// Deletes every object in a staging prefix under `staging_root` that's named
+/// after a timestamp more than [STALE_STAGING_AFTER_HOURS] before `now`.
+async fn delete_stale_staging_prefixes<B: Bucket>(
+    bucket: &B,
+    staging_root: &Path,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    let prefix = staging_root
+        .to_str()
+        .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+    for object in bucket.list_objects(prefix).await? {
+        let timestamp = Path::new(&object.name)
+            .strip_prefix(staging_root)
+            .ok()
+            .and_then(|relative_path| relative_path.iter().next())
+            .and_then(|timestamp| timestamp.to_str())
+            .and_then(|timestamp| {
+                NaiveDateTime::parse_from_str(timestamp, STAGING_TIMESTAMP_FORMAT).ok()
+            });
+        if let Some(timestamp) = timestamp {
+            let age = now.naive_utc() - timestamp;
+            if age.num_hours() >= STALE_STAGING_AFTER_HOURS {
+                bucket.delete_object(&object.name).await?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,397 @@
+use crate::{errors::JobRunnerError::ReportError, pretty_print, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Serialize, Serializer};
+use std::{error::Error, fmt::Write, fs, path::Path, time::Duration};
+
+/// Measurements taken while a job runs.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct JobStats {
+    pub objects_downloaded: usize,
+    /// Objects that weren't downloaded because the job's ledger says they've
+    /// already been processed.
+    pub objects_skipped: usize,
+    /// Outputs uploaded (or published, when outputs are uploaded atomically)
+    /// by this run. A resumed run doesn't count the ones it didn't have to
+    /// upload again.
+    pub objects_uploaded: usize,
+    pub bytes_downloaded: u64,
+    pub bytes_uploaded: u64,
+    #[serde(
+        rename = "download_duration_ms",
+        serialize_with = "pretty_print::serialize_as_millis"
+    )]
+    pub download_duration: Duration,
+    #[serde(
+        rename = "steps_duration_ms",
+        serialize_with = "pretty_print::serialize_as_millis"
+    )]
+    pub steps_duration: Duration,
+    /// How long each of the steps that ran took, in the order they ran, along
+    /// with their index in the job's steps (starting at 0). Steps that were
+    /// skipped, because of `--from-step` or because a resumed run had already
+    /// completed them, are left out.
+    #[serde(serialize_with = "serialize_step_durations")]
+    pub step_durations: Vec<(usize, Duration)>,
+    #[serde(
+        rename = "upload_duration_ms",
+        serialize_with = "pretty_print::serialize_as_millis"
+    )]
+    pub upload_duration: Duration,
+    /// Where the job's outputs were uploaded to, once they have been.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_outputs: Option<String>,
+    /// Set when the job decides not to run its steps, because every input had
+    /// already been processed. Reported as the job's [JobStatus] instead.
+    #[serde(skip)]
+    pub skipped: bool,
+}
+
+/// How long a single step took, the way it's written in reports.
+#[derive(Serialize)]
+struct StepDuration {
+    index: usize,
+    duration_ms: u64,
+}
+
+fn serialize_step_durations<S: Serializer>(
+    durations: &[(usize, Duration)],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_seq(durations.iter().map(|(index, d)| StepDuration {
+        index: *index,
+        duration_ms: d.as_millis() as u64,
+    }))
+}
+
+/// How a job's run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Succeeded,
+    Failed,
+    /// Every input had already been processed, so the job's steps weren't run.
+    Skipped,
+}
+
+impl JobStatus {
+    /// Returns this status the way it's written in reports.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Succeeded => "succeeded",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+/// What went wrong in a failed run.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorReport {
+    pub message: String,
+    /// The chain of errors that caused this one, outermost first.
+    pub sources: Vec<String>,
+}
+
+impl ErrorReport {
+    pub fn new(err: &dyn Error) -> Self {
+        let mut sources = Vec::new();
+        let mut source = err.source();
+        while let Some(s) = source {
+            sources.push(s.to_string());
+            source = s.source();
+        }
+        Self {
+            message: err.to_string(),
+            sources,
+        }
+    }
+}
+
+/// The outcome of one run of one job.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobReport {
+    pub job: String,
+    pub status: JobStatus,
+    pub started_at: DateTime<Utc>,
+    #[serde(
+        rename = "duration_ms",
+        serialize_with = "pretty_print::serialize_as_millis"
+    )]
+    pub duration: Duration,
+    #[serde(flatten)]
+    pub stats: JobStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorReport>,
+}
+
+impl JobReport {
+    pub fn new(
+        job: &str,
+        started_at: DateTime<Utc>,
+        duration: Duration,
+        stats: JobStats,
+        result: &Result<()>,
+    ) -> Self {
+        let status = match result {
+            Err(_) => JobStatus::Failed,
+            Ok(()) if stats.skipped => JobStatus::Skipped,
+            Ok(()) => JobStatus::Succeeded,
+        };
+        Self {
+            job: job.into(),
+            status,
+            started_at,
+            duration,
+            stats,
+            error: result.as_ref().err().map(|e| ErrorReport::new(e)),
+        }
+    }
+}
+
+/// Everything that happened in one invocation of csjr.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub jobs: Vec<JobReport>,
+}
+
+impl Report {
+    /// Writes this [Report] to `path` as pretty-printed JSON.
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(|err| ReportError {
+            path: path.into(),
+            source: Box::new(err),
+        })?;
+        write_file(path, json)
+    }
+
+    /// Writes this [Report] to `path` as a JUnit XML test suite, with one test
+    /// case per job run, so that CI systems can display it.
+    pub fn write_junit(&self, path: &Path) -> Result<()> {
+        write_file(path, self.to_junit())
+    }
+
+    fn to_junit(&self) -> String {
+        let count = |status| self.jobs.iter().filter(|j| j.status == status).count();
+        let total_duration: Duration = self.jobs.iter().map(|j| j.duration).sum();
+        let timestamp = self
+            .jobs
+            .first()
+            .map(|j| j.started_at)
+            .unwrap_or_else(Utc::now)
+            .to_rfc3339_opts(SecondsFormat::Secs, true);
+
+        // Writing to a String can't fail.
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuite name=\"csjr\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\" timestamp=\"{timestamp}\">",
+            self.jobs.len(),
+            count(JobStatus::Failed),
+            count(JobStatus::Skipped),
+            total_duration.as_secs_f64(),
+        );
+        for job in &self.jobs {
+            let _ = write!(
+                xml,
+                "  <testcase classname=\"csjr\" name=\"{}\" time=\"{:.3}\"",
+                escape_xml(&job.job),
+                job.duration.as_secs_f64(),
+            );
+            match (&job.status, &job.error) {
+                (JobStatus::Failed, Some(error)) => {
+                    let _ = writeln!(xml, ">");
+                    let _ = writeln!(
+                        xml,
+                        "    <failure message=\"{}\">{}</failure>",
+                        escape_xml(&error.message),
+                        escape_xml(&error.sources.join("\n")),
+                    );
+                    let _ = writeln!(xml, "  </testcase>");
+                }
+                (JobStatus::Skipped, _) => {
+                    let _ = writeln!(xml, ">");
+                    let _ = writeln!(
+                        xml,
+                        "    <skipped message=\"every input has already been processed\"/>"
+                    );
+                    let _ = writeln!(xml, "  </testcase>");
+                }
+                _ => {
+                    let _ = writeln!(xml, "/>");
+                }
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+fn write_file(path: &Path, contents: String) -> Result<()> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)
+    };
+    write().map_err(|err| ReportError {
+        path: path.into(),
+        source: Box::new(err),
+    })
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod junit_tests {
+    use super::{JobReport, JobStats, Report};
+    use crate::errors::JobRunnerError::JobNotFoundError;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn one_test_case_per_job() {
+        let started_at = Utc.with_ymd_and_hms(2022, 4, 18, 2, 0, 0).unwrap();
+        let report = Report {
+            jobs: vec![
+                JobReport::new(
+                    "resize",
+                    started_at,
+                    Duration::from_millis(1500),
+                    JobStats::default(),
+                    &Ok(()),
+                ),
+                JobReport::new(
+                    "<segment>",
+                    started_at,
+                    Duration::from_millis(250),
+                    JobStats::default(),
+                    &Err(JobNotFoundError {
+                        job_name: "a&b".into(),
+                        close_matches: Vec::new(),
+                    }),
+                ),
+                JobReport::new(
+                    "thumbnail",
+                    started_at,
+                    Duration::ZERO,
+                    JobStats {
+                        objects_skipped: 3,
+                        skipped: true,
+                        ..Default::default()
+                    },
+                    &Ok(()),
+                ),
+            ],
+        };
+        assert_eq!(
+            report.to_junit(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuite name="csjr" tests="3" failures="1" errors="0" skipped="1" time="1.750" timestamp="2022-04-18T02:00:00Z">
+  <testcase classname="csjr" name="resize" time="1.500"/>
+  <testcase classname="csjr" name="&lt;segment&gt;" time="0.250">
+    <failure message="&quot;a&amp;b&quot; not found in the config file"></failure>
+  </testcase>
+  <testcase classname="csjr" name="thumbnail" time="0.000">
+    <skipped message="every input has already been processed"/>
+  </testcase>
+</testsuite>
+"#
+        );
+    }
+}
+
+#[cfg(test)]
+mod json_tests {
+    use super::{JobReport, JobStats, Report};
+    use crate::errors::JobRunnerError::StepNonZeroStatusCodeError;
+    use chrono::{TimeZone, Utc};
+    use serde_json::{json, Value};
+    use std::{fs, time::Duration};
+
+    #[test]
+    fn report_has_each_job_status_counts_and_timings() {
+        // Arrange
+
+        let path = std::env::temp_dir().join("csjr-report-tests/report.json");
+        let _ = fs::remove_file(&path);
+        let started_at = Utc.with_ymd_and_hms(2022, 4, 18, 2, 0, 0).unwrap();
+        let report = Report {
+            jobs: vec![
+                JobReport::new(
+                    "resize",
+                    started_at,
+                    Duration::from_millis(1500),
+                    JobStats {
+                        objects_downloaded: 2,
+                        objects_skipped: 1,
+                        objects_uploaded: 3,
+                        bytes_downloaded: 2048,
+                        bytes_uploaded: 4096,
+                        download_duration: Duration::from_millis(100),
+                        steps_duration: Duration::from_millis(1200),
+                        step_durations: vec![(1, Duration::from_millis(1200))],
+                        upload_duration: Duration::from_millis(200),
+                        remote_outputs: Some("out/20220418T020000Z".into()),
+                        skipped: false,
+                    },
+                    &Ok(()),
+                ),
+                JobReport::new(
+                    "segment",
+                    started_at,
+                    Duration::from_millis(250),
+                    JobStats::default(),
+                    &Err(StepNonZeroStatusCodeError {
+                        step: "false".into(),
+                        code: Some(1),
+                    }),
+                ),
+            ],
+        };
+
+        // Act
+
+        report.write_json(&path).unwrap();
+
+        // Assert
+
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            written["jobs"][0],
+            json!({
+                "job": "resize",
+                "status": "succeeded",
+                "started_at": "2022-04-18T02:00:00Z",
+                "duration_ms": 1500,
+                "objects_downloaded": 2,
+                "objects_skipped": 1,
+                "objects_uploaded": 3,
+                "bytes_downloaded": 2048,
+                "bytes_uploaded": 4096,
+                "download_duration_ms": 100,
+                "steps_duration_ms": 1200,
+                "step_durations": [{"index": 1, "duration_ms": 1200}],
+                "upload_duration_ms": 200,
+                "remote_outputs": "out/20220418T020000Z",
+            })
+        );
+        assert_eq!(written["jobs"][1]["status"], "failed");
+        assert!(written["jobs"][1]["error"]["message"].is_string());
+        assert_eq!(written["jobs"][1]["error"]["sources"], json!([]));
+        assert!(written["jobs"][1].get("remote_outputs").is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
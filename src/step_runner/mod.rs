@@ -1,10 +1,63 @@
 pub mod shell;
 
 use crate::Result;
+use std::{
+    ffi::{OsStr, OsString},
+    fmt::Display,
+};
 
 pub trait StepRunner {
     /// Executes the provided `step` command as a child process. Echos the
     /// child's `stdout` and `stderr` pipes, and blocks until the step
     /// completes.
-    fn run_step(&self, step: &str) -> Result<()>;
+    fn run_step(&self, step: &Step) -> Result<()>;
+}
+
+/// A step that's ready to run: the name of a program, followed by its
+/// arguments.
+///
+/// Words are kept as [OsString]s, so that paths substituted into a step don't
+/// have to be valid UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    words: Vec<OsString>,
+}
+
+impl Step {
+    /// Returns a new `Step` made up of the provided `words`.
+    pub fn new(words: Vec<OsString>) -> Self {
+        Self { words }
+    }
+
+    /// Returns the name of the program this step runs, or `None` if the step
+    /// is empty.
+    pub fn program(&self) -> Option<&OsStr> {
+        self.words.first().map(OsString::as_os_str)
+    }
+
+    /// Returns the arguments this step passes to its program.
+    pub fn args(&self) -> &[OsString] {
+        self.words.get(1..).unwrap_or_default()
+    }
+}
+
+impl From<&str> for Step {
+    /// Splits `step` into words on each space.
+    fn from(step: &str) -> Self {
+        Self::new(step.split(' ').map(OsString::from).collect())
+    }
+}
+
+impl Display for Step {
+    /// Writes this step's words separated by spaces. Bytes that aren't valid
+    /// UTF-8 are replaced with U+FFFD.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, word) in self.words.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", word.to_string_lossy())?;
+        }
+        Ok(())
+    }
 }
@@ -1,39 +1,142 @@
+use super::Step;
 use crate::{
     errors::JobRunnerError::{InvalidStepError, StepNonZeroStatusCodeError},
+    pretty_print::{self, Event, LogFormat, Verbosity},
     Result,
 };
-use std::process;
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process, thread,
+};
+use tracing::instrument;
 
 pub struct Runner {}
 
 impl super::StepRunner for Runner {
-    fn run_step(&self, step: &str) -> Result<()> {
-        // Build a process::Command.
-        let mut iter = step.split(' ');
-
-        let program_name = iter
-            .next()
-            .ok_or_else(|| InvalidStepError { step: step.into() })?;
-        let mut command = &mut process::Command::new(program_name);
-
-        for arg in iter {
-            command = command.arg(arg);
-        }
+    #[instrument(skip_all, err, fields(step = %step))]
+    fn run_step(&self, step: &Step) -> Result<()> {
+        let command = &mut command(step)?;
+        pretty_print::status_at(Verbosity::Debug, "Executing", &format!("{command:?}"), true)?;
 
-        // Run the process::Command and wait for it to finish.
-        let mut child = command
-            .stdout(process::Stdio::inherit())
-            .stderr(process::Stdio::inherit())
-            .spawn()?;
+        // Run the process::Command and wait for it to finish. When logging
+        // JSON, the child's output is logged as events too, so that it doesn't
+        // get mixed in with the JSON lines. When a spinner is drawn, it's
+        // logged so that the spinner can get out of its way.
+        let status =
+            if pretty_print::log_format() == LogFormat::Json || pretty_print::is_progress_shown() {
+                run_logging_output(command)?
+            } else {
+                let mut child = command
+                    .stdout(process::Stdio::inherit())
+                    .stderr(process::Stdio::inherit())
+                    .spawn()?;
+                child.wait()?
+            };
 
-        let status = child.wait()?;
         if status.success() {
             Ok(())
         } else {
             Err(StepNonZeroStatusCodeError {
-                step: step.into(),
+                step: step.to_string(),
                 code: status.code(),
             })
         }
     }
 }
+
+/// Builds a [process::Command] that runs `step`: the program named by its
+/// first word, with the rest of its words as arguments.
+pub(crate) fn command(step: &Step) -> Result<process::Command> {
+    let program_name = step.program().ok_or_else(|| InvalidStepError {
+        step: step.to_string(),
+    })?;
+    let mut command = process::Command::new(program_name);
+    command.args(step.args());
+    Ok(command)
+}
+
+/// Runs `command` with its stdout and stderr piped, logging each line of them
+/// as it's written, and returns its exit status once it's finished.
+///
+/// The child is always waited for, even if logging its output fails, so that
+/// it isn't left running; the logging error is returned afterwards.
+fn run_logging_output(command: &mut process::Command) -> io::Result<process::ExitStatus> {
+    let mut child = command
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::piped())
+        .spawn()?;
+    let stderr = child.stderr.take().expect("child's stderr is piped");
+    let stderr_logger = thread::spawn(move || log_lines("stderr", stderr));
+    let stdout_result = log_lines(
+        "stdout",
+        child.stdout.take().expect("child's stdout is piped"),
+    );
+    let stderr_result = stderr_logger
+        .join()
+        .expect("thread logging the child's stderr panicked");
+    let status = child.wait()?;
+    stdout_result.and(stderr_result)?;
+    Ok(status)
+}
+
+/// Logs each line read from `output` as a [Event::StepOutput] event.
+fn log_lines(stream: &str, output: impl Read) -> io::Result<()> {
+    for_each_line(output, |line| {
+        pretty_print::event(&Event::StepOutput { stream, line })
+    })
+}
+
+/// Calls `f` with each line read from `output`, without its line ending.
+/// Lines that aren't valid UTF-8 have U+FFFD REPLACEMENT CHARACTER in place of
+/// the invalid bytes, since steps can print anything.
+fn for_each_line(output: impl Read, mut f: impl FnMut(&str) -> io::Result<()>) -> io::Result<()> {
+    let mut output = BufReader::new(output);
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if output.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        f(String::from_utf8_lossy(&line).trim_end_matches(['\n', '\r']))?;
+    }
+}
+
+#[cfg(test)]
+mod run_logging_output_tests {
+    use std::process;
+
+    #[test]
+    fn output_that_is_not_utf8_is_logged() {
+        // `\377` is never valid in UTF-8. Output is piped like this whenever
+        // a spinner is drawn, or JSON is logged.
+        let mut command = process::Command::new("sh");
+        command.args(["-c", "printf 'caf\\377\\n' && printf '\\377' >&2"]);
+
+        let status = super::run_logging_output(&mut command).unwrap();
+
+        assert!(status.success());
+    }
+
+    #[test]
+    fn exit_status_is_kept() {
+        let mut command = process::Command::new("sh");
+        command.args(["-c", "printf '\\377\\n' && exit 3"]);
+
+        let status = super::run_logging_output(&mut command).unwrap();
+
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn lines_that_are_not_utf8_are_read_lossily() {
+        let mut lines = Vec::new();
+
+        super::for_each_line(&b"caf\xe9\r\nno newline at the end"[..], |line| {
+            lines.push(line.to_string());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(lines, vec!["caf\u{fffd}", "no newline at the end"]);
+    }
+}
@@ -0,0 +1,89 @@
+/// Most suggestions that are returned by [close_matches].
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the names in `candidates` that are close to `name`, like ones that
+/// are only a typo or two away from it, or that contain it. Closest matches
+/// come first.
+pub(crate) fn close_matches<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a String>,
+) -> Vec<String> {
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut matches: Vec<(usize, &String)> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let lowercase = candidate.to_lowercase();
+            let distance = edit_distance(&name, &lowercase);
+            if distance <= max_distance || (!name.is_empty() && lowercase.contains(&name)) {
+                Some((distance, candidate))
+            } else {
+                None
+            }
+        })
+        .collect();
+    matches.sort();
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.clone())
+        .collect()
+}
+
+/// Returns the Levenshtein distance between `a` and `b`: how many characters
+/// have to be inserted, deleted, or substituted to turn one into the other.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_row[j] + usize::from(a_char != *b_char);
+            let insertion = row[j] + 1;
+            let deletion = previous_row[j + 1] + 1;
+            row.push(substitution.min(insertion).min(deletion));
+        }
+        previous_row = row;
+    }
+    previous_row[b.len()]
+}
+
+#[cfg(test)]
+mod close_matches_tests {
+    use super::close_matches;
+
+    fn job_names() -> Vec<String> {
+        vec![
+            "resize_images".into(),
+            "resize_videos".into(),
+            "transcribe".into(),
+            "backup".into(),
+        ]
+    }
+
+    #[test]
+    fn typo() {
+        assert_eq!(
+            close_matches("resize_imgaes", &job_names()),
+            vec!["resize_images"]
+        );
+    }
+
+    #[test]
+    fn different_case() {
+        assert_eq!(close_matches("Backup", &job_names()), vec!["backup"]);
+    }
+
+    #[test]
+    fn part_of_name() {
+        assert_eq!(
+            close_matches("resize", &job_names()),
+            vec!["resize_images", "resize_videos"]
+        );
+    }
+
+    #[test]
+    fn nothing_close() {
+        assert!(close_matches("deploy", &job_names()).is_empty());
+    }
+}
@@ -0,0 +1,39 @@
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+use std::error::Error;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Exports the spans that csjr records to an OpenTelemetry collector over
+/// OTLP, until it's shut down.
+pub struct Telemetry {
+    provider: TracerProvider,
+}
+
+impl Telemetry {
+    /// Starts exporting spans over gRPC. Where to is configured the usual
+    /// way, with the `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable
+    /// (`http://localhost:4317` by default).
+    pub fn init() -> Result<Self, Box<dyn Error>> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .build()?;
+        let provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new([KeyValue::new(
+                "service.name",
+                env!("CARGO_PKG_NAME"),
+            )]))
+            .build();
+        tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("csjr")))
+            .try_init()?;
+        Ok(Self { provider })
+    }
+
+    /// Exports any spans that haven't been exported yet, and stops
+    /// exporting.
+    pub fn shutdown(self) -> Result<(), Box<dyn Error>> {
+        self.provider.shutdown()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    bucket::{self, Bucket},
+    errors::JobRunnerError::{IOError, InvalidPathError},
+    report::JobStats,
+    Checkpoint, Job, JobRunner, PathKeyInConfig, Result,
+};
+use chrono::Utc;
+use std::io;
+use tracing::instrument;
+
+impl Job {
+    /// Downloads everything in this [Job]'s `path_to_remote_inputs` directory
+    /// into `path_to_local_inputs`, without running any of its steps.
+    ///
+    /// Since nothing gets processed, the job's ledger is neither consulted nor
+    /// updated.
+    #[instrument(skip_all, fields(bucket = self.bucket_name))]
+    pub async fn pull<B: Bucket>(&self, bucket: &B, stats: &mut JobStats) -> Result<()> {
+        let prefix = self
+            .path_to_remote_inputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+        let objects = bucket.list_objects(prefix).await?;
+        let objects: Vec<&bucket::Object> = objects.iter().collect();
+        self.download_objects(bucket, &objects, &mut Checkpoint::default(), stats)
+            .await
+    }
+
+    /// Uploads everything in this [Job]'s `path_to_local_outputs` directory
+    /// the same way a run would, without running any of its steps. Run
+    /// identifiers in `path_to_remote_outputs` are filled in, and outputs are
+    /// published atomically if the job has `atomic_outputs` set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `io::Error` if the `path_to_local_outputs` directory doesn't
+    /// exist on disk, since there'd be nothing to upload.
+    #[instrument(skip_all, fields(bucket = self.bucket_name))]
+    pub async fn push<B: Bucket>(&self, bucket: &B, stats: &mut JobStats) -> Result<()> {
+        if !self.path_to_local_outputs.is_dir() {
+            return Err(IOError(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!(
+                    "There are no outputs to push, because \"{}\" isn't a directory",
+                    self.path_to_local_outputs.display()
+                ),
+            )));
+        }
+        let mut checkpoint = Checkpoint::default();
+        match self.scoped_to_run(Utc::now())? {
+            Some(job) => {
+                job.upload_outputs(bucket, &self.path_to_remote_outputs, &mut checkpoint, stats)
+                    .await?;
+                job.update_latest_pointer(bucket, &self.path_to_remote_outputs)
+                    .await
+            }
+            None => {
+                self.upload_outputs(bucket, &self.path_to_remote_outputs, &mut checkpoint, stats)
+                    .await
+            }
+        }
+    }
+}
+
+impl<'a> JobRunner<'a> {
+    /// Downloads the inputs of the job named `job_name`, without running it.
+    /// See [Job::pull].
+    pub async fn pull(&self, job_name: &str) -> Result<()> {
+        let job = self.job(job_name)?;
+        job.pull(&job.bucket()?, &mut JobStats::default()).await
+    }
+
+    /// Uploads the outputs of the job named `job_name`, without running it.
+    /// See [Job::push].
+    pub async fn push(&self, job_name: &str) -> Result<()> {
+        let job = self.job(job_name)?;
+        job.push(&job.bucket()?, &mut JobStats::default()).await
+    }
+}
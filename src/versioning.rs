@@ -0,0 +1,258 @@
+use crate::{
+    bucket::Bucket,
+    errors::JobRunnerError::{GitCommitNotFoundError, InvalidPathError},
+    Job, PathKeyInConfig, Result,
+};
+use chrono::{DateTime, Utc};
+use std::{
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Substituted with the time a run started, like `20220418T020000Z`.
+const TIMESTAMP_PLACEHOLDER: &str = "[timestamp]";
+/// Substituted with an identifier that's unique to each run.
+const RUN_ID_PLACEHOLDER: &str = "[run_id]";
+/// Substituted with the (short) hash of the commit that's checked out in the
+/// git repo of the current working directory, which isn't necessarily the one
+/// that the config file is in.
+const GIT_SHA_PLACEHOLDER: &str = "[git_sha]";
+
+/// Name of the object that points to the most recent run's outputs.
+const LATEST_POINTER: &str = "latest";
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+impl Job {
+    /// If this [Job]'s `path_to_remote_outputs` contains any run identifiers,
+    /// returns a copy of this [Job] with them filled in for a run that
+    /// started at `started_at`. Otherwise, returns `None`.
+    pub(crate) fn scoped_to_run(&self, started_at: DateTime<Utc>) -> Result<Option<Job>> {
+        let path_to_remote_outputs = self
+            .path_to_remote_outputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+        if !has_run_identifier(path_to_remote_outputs) {
+            return Ok(None);
+        }
+
+        let timestamp = started_at.format(TIMESTAMP_FORMAT).to_string();
+        let mut scoped = path_to_remote_outputs
+            .replace(TIMESTAMP_PLACEHOLDER, &timestamp)
+            .replace(
+                RUN_ID_PLACEHOLDER,
+                &format!("{timestamp}-{}", process::id()),
+            );
+        if scoped.contains(GIT_SHA_PLACEHOLDER) {
+            scoped = scoped.replace(GIT_SHA_PLACEHOLDER, &current_git_sha()?);
+        }
+        Ok(Some(Job {
+            path_to_remote_outputs: scoped.into(),
+            ..self.clone()
+        }))
+    }
+
+    /// Points the `latest` object next to this run's outputs at them. The
+    /// pointer lives in the directory that `unscoped_path_to_remote_outputs`
+    /// has its first run identifier in, and its contents are this [Job]'s
+    /// `path_to_remote_outputs`.
+    pub(crate) async fn update_latest_pointer<B: Bucket>(
+        &self,
+        bucket: &B,
+        unscoped_path_to_remote_outputs: &Path,
+    ) -> Result<()> {
+        let path_to_remote_outputs = self
+            .path_to_remote_outputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+        let pointer = latest_pointer(unscoped_path_to_remote_outputs);
+        let pointer = pointer
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteOutputs))?;
+        bucket
+            .write_object(
+                pointer,
+                path_to_remote_outputs.as_bytes().to_vec(),
+                "text/plain",
+            )
+            .await
+    }
+}
+
+fn has_run_identifier(s: &str) -> bool {
+    [
+        TIMESTAMP_PLACEHOLDER,
+        RUN_ID_PLACEHOLDER,
+        GIT_SHA_PLACEHOLDER,
+    ]
+    .iter()
+    .any(|placeholder| s.contains(placeholder))
+}
+
+/// Returns the directory that contains the first run identifier in
+/// `unscoped_path_to_remote_outputs`, which is the same for every run. If
+/// there aren't any run identifiers, that's the whole path.
+pub(crate) fn unscoped_prefix(unscoped_path_to_remote_outputs: &Path) -> PathBuf {
+    unscoped_path_to_remote_outputs
+        .iter()
+        .take_while(|component| !has_run_identifier(&component.to_string_lossy()))
+        .collect()
+}
+
+/// Returns where the `latest` pointer for `unscoped_path_to_remote_outputs`
+/// lives: in the directory that contains its first run identifier.
+fn latest_pointer(unscoped_path_to_remote_outputs: &Path) -> PathBuf {
+    unscoped_prefix(unscoped_path_to_remote_outputs).join(LATEST_POINTER)
+}
+
+/// Returns the short hash of the commit that's checked out in the git repo of
+/// the current working directory.
+fn current_git_sha() -> Result<String> {
+    git_sha_in(Path::new("."))
+}
+
+/// Returns the short hash of the commit that's checked out in the git repo
+/// that `dir` is in.
+fn git_sha_in(dir: &Path) -> Result<String> {
+    let output = process::Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .map_err(|err| GitCommitNotFoundError {
+            source: Box::new(err),
+        })?;
+    if !output.status.success() {
+        return Err(GitCommitNotFoundError {
+            source: String::from_utf8_lossy(&output.stderr).trim().into(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().into())
+}
+
+#[cfg(test)]
+mod scoped_to_run_tests {
+    use crate::{CloudServiceProvider, Job, NotifyHooks};
+    use chrono::{TimeZone, Utc};
+    use std::{path::PathBuf, process};
+
+    fn job(path_to_remote_outputs: &str) -> Job {
+        Job {
+            cloud_service_provider: CloudServiceProvider::GCP,
+            bucket_name: "foo".into(),
+            path_to_remote_inputs: "in".into(),
+            path_to_local_inputs: "in".into(),
+            path_to_local_outputs: "out".into(),
+            path_to_remote_outputs: path_to_remote_outputs.into(),
+            steps: Vec::new(),
+            schedule: None,
+            atomic_outputs: false,
+            notify: NotifyHooks::default(),
+        }
+    }
+
+    fn scoped(path_to_remote_outputs: &str) -> Option<PathBuf> {
+        let started_at = Utc.with_ymd_and_hms(2022, 4, 18, 2, 0, 0).unwrap();
+        job(path_to_remote_outputs)
+            .scoped_to_run(started_at)
+            .unwrap()
+            .map(|job| job.path_to_remote_outputs)
+    }
+
+    #[test]
+    fn timestamp_is_when_run_started() {
+        assert_eq!(
+            scoped("results/[timestamp]"),
+            Some(PathBuf::from("results/20220418T020000Z"))
+        );
+    }
+
+    #[test]
+    fn run_id_is_timestamp_and_process_id() {
+        assert_eq!(
+            scoped("results/[run_id]/seg"),
+            Some(PathBuf::from(format!(
+                "results/20220418T020000Z-{}/seg",
+                process::id()
+            )))
+        );
+    }
+
+    #[test]
+    fn path_without_run_identifiers_is_not_scoped() {
+        assert_eq!(scoped("results/seg"), None);
+    }
+}
+
+#[cfg(test)]
+mod git_sha_tests {
+    use super::git_sha_in;
+    use std::{fs, path::Path, process};
+
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = process::Command::new("git")
+            .args(["-c", "user.name=csjr", "-c", "user.email=csjr@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap().trim().into()
+    }
+
+    #[test]
+    fn git_sha_is_checked_out_commit() {
+        // A repo of its own, so this works even when csjr isn't built from a
+        // git checkout.
+        let repo = std::env::temp_dir().join("csjr-git-sha-tests");
+        let _ = fs::remove_dir_all(&repo);
+        fs::create_dir_all(&repo).unwrap();
+        git(&repo, &["init", "-q"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "First"]);
+        let first = git(&repo, &["rev-parse", "--short", "HEAD"]);
+        git(&repo, &["commit", "-q", "--allow-empty", "-m", "Second"]);
+        git(&repo, &["checkout", "-q", &first]);
+
+        let sha = git_sha_in(&repo).unwrap();
+
+        assert_eq!(sha, first);
+        fs::remove_dir_all(&repo).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod latest_pointer_tests {
+    use super::{latest_pointer, unscoped_prefix};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn run_identifier_is_whole_component() {
+        assert_eq!(
+            latest_pointer(Path::new("results/seg/[timestamp]")),
+            PathBuf::from("results/seg/latest")
+        );
+        assert_eq!(
+            latest_pointer(Path::new("results/[run_id]/seg")),
+            PathBuf::from("results/latest")
+        );
+    }
+
+    #[test]
+    fn run_identifier_is_part_of_component() {
+        assert_eq!(
+            latest_pointer(Path::new("results/seg-[git_sha]")),
+            PathBuf::from("results/latest")
+        );
+        assert_eq!(
+            latest_pointer(Path::new("[timestamp]")),
+            PathBuf::from("latest")
+        );
+    }
+
+    #[test]
+    fn unscoped_prefix_without_run_identifiers_is_whole_path() {
+        assert_eq!(
+            unscoped_prefix(Path::new("results/seg")),
+            PathBuf::from("results/seg")
+        );
+    }
+}
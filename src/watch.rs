@@ -0,0 +1,120 @@
+use crate::{
+    bucket::{Bucket, Object},
+    errors::JobRunnerError::InvalidPathError,
+    pretty_print, Checkpoint, Job, JobRunner, PathKeyInConfig, Result,
+};
+use std::time::Duration;
+
+impl<'a> JobRunner<'a> {
+    /// Polls the `path_to_remote_inputs` directory of each job in `job_names`
+    /// every `interval`, and runs that job on any objects that its [Ledger]
+    /// says haven't been processed yet. If `job_names` is empty, watches all
+    /// jobs.
+    ///
+    /// Since an object is only recorded in a job's ledger once the job has
+    /// run on it successfully, failed runs are retried on the next poll. If
+    /// `skip_existing` is true, objects that are already in the bucket when
+    /// watching starts are recorded in their job's ledger without running the
+    /// job on them (see [JobRunner::skip_existing]).
+    ///
+    /// Never returns unless something goes wrong before the first poll.
+    ///
+    /// [Ledger]: crate::Ledger
+    pub async fn watch(
+        &mut self,
+        job_names: &[String],
+        interval: Duration,
+        skip_existing: bool,
+    ) -> Result<()> {
+        let job_names: Vec<&str> = if job_names.is_empty() {
+            self.config.jobs.keys().map(String::as_str).collect()
+        } else {
+            job_names.iter().map(String::as_str).collect()
+        };
+        for job_name in &job_names {
+            self.job(job_name)?;
+        }
+
+        if skip_existing {
+            for job_name in &job_names {
+                let job = self.job(job_name)?;
+                self.skip_existing(job_name, &job.bucket()?).await?;
+            }
+        }
+
+        pretty_print::status(
+            "Watching",
+            &format!("{} every {}s", job_names.join(", "), interval.as_secs()),
+            false,
+        )?;
+        loop {
+            for job_name in &job_names {
+                let result = match self.job(job_name).and_then(Job::bucket) {
+                    Ok(bucket) => self.poll(job_name, &bucket).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    pretty_print::error(&e)?;
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Lists the objects in the `path_to_remote_inputs` directory of the job
+    /// named `job_name` in `bucket`, and runs that job on the ones that its
+    /// ledger says haven't been processed yet. Doesn't run the job at all if
+    /// there aren't any.
+    pub async fn poll<B: Bucket>(&mut self, job_name: &str, bucket: &B) -> Result<()> {
+        let job = self.job(job_name)?;
+        let ledger = self.ledger(job_name)?;
+        let prefix = job
+            .path_to_remote_inputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+        let new_objects: Vec<Object> = bucket
+            .list_objects(prefix)
+            .await?
+            .into_iter()
+            .filter(|o| !ledger.contains(o))
+            .collect();
+        if new_objects.is_empty() {
+            return Ok(());
+        }
+
+        pretty_print::status(
+            "Found",
+            &format!("{} new object(s) for {job_name}", new_objects.len()),
+            false,
+        )?;
+        // The next poll retries a failed run on whatever's still missing from
+        // the ledger, so there's no need for a checkpoint file to resume it.
+        self.run_job_on_bucket(
+            job_name,
+            bucket,
+            Some(&new_objects),
+            Some(Checkpoint::default()),
+        )
+        .await
+    }
+
+    /// Records every object that's in the `path_to_remote_inputs` directory
+    /// of the job named `job_name` in `bucket` right now in that job's
+    /// ledger, as if the job had already processed them, so that neither
+    /// watching nor running the job processes them.
+    pub async fn skip_existing<B: Bucket>(&self, job_name: &str, bucket: &B) -> Result<()> {
+        let job = self.job(job_name)?;
+        let prefix = job
+            .path_to_remote_inputs
+            .to_str()
+            .ok_or(InvalidPathError(PathKeyInConfig::RemoteInputs))?;
+        let objects = bucket.list_objects(prefix).await?;
+        self.ledger(job_name)?.record(&objects)?;
+        pretty_print::status(
+            "Skipping",
+            &format!("{} existing object(s) for {job_name}", objects.len()),
+            false,
+        )?;
+        Ok(())
+    }
+}
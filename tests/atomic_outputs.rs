@@ -0,0 +1,130 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    self, report::JobStats, step_runner::shell, Job, Ledger, RunOptions,
+};
+use std::{fs, path::Path};
+
+#[tokio::test]
+async fn atomic_outputs_are_published_with_success_marker() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("atomic_outputs");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = Job {
+        atomic_outputs: true,
+        ..common::job(&tmp_dir_path, "bar")
+    };
+    let bucket = common::MemoryBucket::with_objects(&[
+        "foo/foo.txt",
+        "bar/_SUCCESS",
+        ".csjr-staging/bar/20000101T000000Z/abandoned.txt",
+    ]);
+    let step_runner = shell::Runner {};
+    let mut stats = JobStats::default();
+
+    // Act
+
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut Ledger::default(),
+        &mut stats,
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    assert_eq!(stats.objects_uploaded, 1);
+    assert_eq!(
+        bucket.object_names(),
+        vec!["bar/_SUCCESS", "bar/foo.txt", "foo/foo.txt"]
+    );
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn atomic_outputs_with_leading_slash_are_staged_and_published() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("atomic_outputs_leading_slash");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = Job {
+        atomic_outputs: true,
+        ..common::job(&tmp_dir_path, "/bar")
+    };
+    let bucket = common::MemoryBucket::with_objects(&[
+        "foo/foo.txt",
+        ".csjr-staging/bar/20000101T000000Z/abandoned.txt",
+    ]);
+    let step_runner = shell::Runner {};
+
+    // Act
+
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    assert_eq!(
+        bucket.object_names(),
+        vec!["bar/_SUCCESS", "bar/foo.txt", "foo/foo.txt"]
+    );
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn run_scoped_atomic_outputs_clean_up_earlier_runs_staging() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("atomic_outputs_run_scoped");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = Job {
+        atomic_outputs: true,
+        ..common::job(&tmp_dir_path, "bar/[timestamp]")
+    };
+    let bucket = common::MemoryBucket::with_objects(&[
+        "foo/foo.txt",
+        ".csjr-staging/bar/20000101T000000Z/abandoned.txt",
+    ]);
+    let step_runner = shell::Runner {};
+
+    // Act
+
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    let object_names = bucket.object_names();
+    assert!(
+        object_names
+            .iter()
+            .all(|name| !name.starts_with(".csjr-staging")),
+        "{object_names:?} still has staged objects"
+    );
+    assert!(object_names
+        .iter()
+        .any(|name| name.starts_with("bar/") && name.ends_with("/foo.txt")));
+    assert!(object_names.contains(&"bar/latest".to_string()));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
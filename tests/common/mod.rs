@@ -1,7 +1,32 @@
-use std::{fs, path::Path};
+// Not every integration test uses every mock.
+#![allow(dead_code)]
+
+use std::{collections::BTreeMap, fs, io, path::Path, sync::Mutex};
 
 use async_trait::async_trait;
-use cloud_storage_job_runner::{bucket::Bucket, Result};
+use chrono::{DateTime, Utc};
+use cloud_storage_job_runner::{
+    bucket::{keys, Bucket, Object, ObjectDetails},
+    CloudServiceProvider, Job, NotifyHooks, Result,
+};
+
+/// Returns a job that copies the objects in the bucket's "foo" directory to
+/// `path_to_remote_outputs`, with its local inputs and outputs inside
+/// `tmp_dir_path`.
+pub fn job(tmp_dir_path: &Path, path_to_remote_outputs: &str) -> Job {
+    Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: tmp_dir_path.join("inputs"),
+        path_to_local_outputs: tmp_dir_path.join("outputs"),
+        path_to_remote_outputs: path_to_remote_outputs.into(),
+        steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    }
+}
 
 /// Mocked implementation of a [Bucket]. Useful for writing integration tests
 /// where interactions with a file storage service in the cloud aren't what's
@@ -10,6 +35,42 @@ pub struct DummyBucket {}
 
 #[async_trait]
 impl Bucket for DummyBucket {
+    /// Pretends to list the objects in the provided `prefix` directory in the
+    /// cloud. In reality, returns a single object named "foo.txt".
+    async fn list_objects(&self, _prefix: &str) -> Result<Vec<Object>> {
+        Ok(vec![Object {
+            name: "foo.txt".into(),
+            generation: 1,
+        }])
+    }
+
+    /// Pretends to list the objects in the provided `prefix` directory in the
+    /// cloud. In reality, returns the details of a single object named
+    /// "foo.txt".
+    async fn list_object_details(&self, _prefix: &str) -> Result<Vec<ObjectDetails>> {
+        Ok(vec![ObjectDetails {
+            name: "foo.txt".into(),
+            size: 65,
+            updated: DateTime::<Utc>::UNIX_EPOCH,
+            content_type: Some("text/plain".into()),
+        }])
+    }
+
+    /// Pretends to download the object named `object_name` from the cloud.
+    /// Writes a text file with that name in the provided
+    /// `path_to_local_inputs` directory, creating it if necessary.
+    async fn download_object(
+        &self,
+        object_name: &str,
+        _path_to_remote_inputs: &Path,
+        path_to_local_inputs: &Path,
+    ) -> Result<u64> {
+        let contents = "Whoever is the owner of the white sedan, you left your lights on.";
+        fs::create_dir_all(path_to_local_inputs)?;
+        fs::write(path_to_local_inputs.join(object_name), contents)?;
+        Ok(contents.len() as u64)
+    }
+
     /// Pretends to download files from the provided `path_to_remote_inputs`
     /// directory in the cloud. Creates the provided `path_to_local_inputs`
     /// directory on disk, and writes a single text file in that directory.
@@ -17,22 +78,196 @@ impl Bucket for DummyBucket {
         &self,
         _path_to_remote_inputs: &Path,
         path_to_local_inputs: &Path,
-    ) -> Result<()> {
+    ) -> Result<u64> {
+        let contents = "Whoever is the owner of the white sedan, you left your lights on.";
         fs::create_dir(path_to_local_inputs)?;
-        fs::write(
-            path_to_local_inputs.join("foo.txt"),
-            "Whoever is the owner of the white sedan, you left your lights on.",
-        )?;
+        fs::write(path_to_local_inputs.join("foo.txt"), contents)?;
+        Ok(contents.len() as u64)
+    }
+
+    /// Pretends to write an object to the cloud. In reality, it does nothing.
+    async fn write_object(
+        &self,
+        _object_name: &str,
+        _contents: Vec<u8>,
+        _mime_type: &str,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pretends to copy an object in the cloud. In reality, it does nothing.
+    async fn copy_object(&self, _from: &str, _to: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pretends to delete an object in the cloud. In reality, it does nothing.
+    async fn delete_object(&self, _object_name: &str) -> Result<()> {
         Ok(())
     }
 
+    /// Pretends to upload a file to the cloud. In reality, it does nothing.
+    async fn upload_object(
+        &self,
+        _local_file_path: &Path,
+        _path_to_local_outputs: &Path,
+        _path_to_remote_outputs: &Path,
+    ) -> Result<u64> {
+        Ok(0)
+    }
+
     /// Pretends to upload files in the provided `path_to_local_outputs`
     /// directory to the cloud. In reality, it does nothing.
     async fn upload_outputs(
         &self,
         _path_to_local_outputs: &Path,
         _path_to_remote_outputs: &Path,
+    ) -> Result<u64> {
+        Ok(0)
+    }
+}
+
+/// Implementation of a [Bucket] that keeps its objects in memory. Useful for
+/// writing integration tests that care about which objects end up where.
+///
+/// Like in a real bucket, every object has a generation, which goes up each
+/// time the object is written.
+#[derive(Default)]
+pub struct MemoryBucket {
+    objects: Mutex<BTreeMap<String, MemoryObject>>,
+}
+
+struct MemoryObject {
+    contents: Vec<u8>,
+    generation: i64,
+}
+
+impl MemoryBucket {
+    /// Returns a new `MemoryBucket` that contains objects with the provided
+    /// names, each with some placeholder contents.
+    pub fn with_objects(names: &[&str]) -> Self {
+        let bucket = Self::default();
+        for name in names {
+            bucket.insert(name, b"Bucket contents".to_vec());
+        }
+        bucket
+    }
+
+    /// Creates an object named `name` with the provided `contents`, or
+    /// overwrites it (and bumps its generation) if it already exists.
+    pub fn insert(&self, name: &str, contents: Vec<u8>) {
+        let mut objects = self.objects.lock().unwrap();
+        let generation = objects.get(name).map_or(1, |o| o.generation + 1);
+        objects.insert(
+            name.into(),
+            MemoryObject {
+                contents,
+                generation,
+            },
+        );
+    }
+
+    /// Returns the names of all of the objects in this bucket, in order.
+    pub fn object_names(&self) -> Vec<String> {
+        self.objects.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+#[async_trait]
+impl Bucket for MemoryBucket {
+    async fn list_objects(&self, prefix: &str) -> Result<Vec<Object>> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| keys::is_listed_inside(name, Path::new(prefix)))
+            .map(|(name, object)| Object {
+                name: name.clone(),
+                generation: object.generation,
+            })
+            .collect())
+    }
+
+    async fn list_object_details(&self, prefix: &str) -> Result<Vec<ObjectDetails>> {
+        Ok(self
+            .objects
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(name, _)| keys::is_listed_inside(name, Path::new(prefix)))
+            .map(|(name, object)| ObjectDetails {
+                name: name.clone(),
+                size: object.contents.len() as u64,
+                updated: DateTime::<Utc>::UNIX_EPOCH,
+                content_type: None,
+            })
+            .collect())
+    }
+
+    async fn download_object(
+        &self,
+        object_name: &str,
+        path_to_remote_inputs: &Path,
+        path_to_local_inputs: &Path,
+    ) -> Result<u64> {
+        let contents = self.objects.lock().unwrap()[object_name].contents.clone();
+        let local_file_path =
+            keys::local_path(object_name, path_to_remote_inputs, path_to_local_inputs)?;
+        let num_bytes = contents.len() as u64;
+        fs::create_dir_all(local_file_path.parent().unwrap())?;
+        fs::write(local_file_path, contents)?;
+        Ok(num_bytes)
+    }
+
+    async fn write_object(
+        &self,
+        object_name: &str,
+        contents: Vec<u8>,
+        _mime_type: &str,
     ) -> Result<()> {
+        self.insert(object_name, contents);
+        Ok(())
+    }
+
+    async fn copy_object(&self, from: &str, to: &str) -> Result<()> {
+        let contents = self
+            .objects
+            .lock()
+            .unwrap()
+            .get(from)
+            .map(|o| o.contents.clone())
+            .ok_or_else(not_found)?;
+        self.insert(to, contents);
+        Ok(())
+    }
+
+    async fn delete_object(&self, object_name: &str) -> Result<()> {
+        self.objects
+            .lock()
+            .unwrap()
+            .remove(object_name)
+            .ok_or_else(not_found)?;
         Ok(())
     }
+
+    async fn upload_object(
+        &self,
+        local_file_path: &Path,
+        path_to_local_outputs: &Path,
+        path_to_remote_outputs: &Path,
+    ) -> Result<u64> {
+        let object_name = keys::object_name(
+            local_file_path,
+            path_to_local_outputs,
+            path_to_remote_outputs,
+        )?;
+        let contents = fs::read(local_file_path)?;
+        let num_bytes = contents.len() as u64;
+        self.insert(&object_name, contents);
+        Ok(num_bytes)
+    }
+}
+
+fn not_found() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "object not found")
 }
@@ -0,0 +1,116 @@
+use cloud_storage_job_runner::{Config, JobRunnerError};
+use std::{fs, path::Path};
+
+#[test]
+fn missing_config_file_is_read_error() {
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("does-not-exist.yaml");
+
+    let err = Config::from_path(&path).unwrap_err();
+
+    match err {
+        JobRunnerError::ConfigReadError { path: err_path, .. } => assert_eq!(err_path, path),
+        err => panic!("expected a ConfigReadError, got {err:?}"),
+    }
+}
+
+#[test]
+fn invalid_config_file_is_parse_error_with_location() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(&tmp_dir_path).unwrap();
+    let path = tmp_dir_path.join("config.yaml");
+    fs::write(
+        &path,
+        "jobs:\n  foo:\n    cloud_service_provider: AWS\n    bucket_name: foo\n",
+    )
+    .unwrap();
+
+    // Act
+
+    let err = Config::from_path(&path).unwrap_err();
+
+    // Assert
+
+    match err {
+        JobRunnerError::ConfigParseError {
+            path: err_path,
+            line,
+            ..
+        } => {
+            assert_eq!(err_path, path);
+            assert_eq!(line, Some(3));
+        }
+        err => panic!("expected a ConfigParseError, got {err:?}"),
+    }
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[test]
+fn config_is_found_in_closest_parent_directory() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_find_parent");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let nested_dir = tmp_dir_path.join("project/src/nested");
+    fs::create_dir_all(&nested_dir).unwrap();
+    fs::write(tmp_dir_path.join("csjr.yaml"), "jobs: {}\n").unwrap();
+    fs::write(tmp_dir_path.join("project/csjr.yaml"), "jobs: {}\n").unwrap();
+    let user_config_dir = tmp_dir_path.join("xdg");
+    fs::create_dir_all(user_config_dir.join("csjr")).unwrap();
+    fs::write(user_config_dir.join("csjr/config.yaml"), "jobs: {}\n").unwrap();
+
+    // Act
+
+    let path = Config::find_from(&nested_dir, Some(&user_config_dir)).unwrap();
+
+    // Assert
+
+    assert_eq!(path, tmp_dir_path.join("project/csjr.yaml"));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[test]
+fn config_falls_back_to_user_config_directory() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_find_user");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let project_dir = tmp_dir_path.join("project");
+    fs::create_dir_all(&project_dir).unwrap();
+    let user_config_dir = tmp_dir_path.join("xdg");
+    fs::create_dir_all(user_config_dir.join("csjr")).unwrap();
+    fs::write(user_config_dir.join("csjr/config.yaml"), "jobs: {}\n").unwrap();
+
+    // Act
+
+    let path = Config::find_from(&project_dir, Some(&user_config_dir)).unwrap();
+
+    // Assert
+
+    assert_eq!(path, user_config_dir.join("csjr/config.yaml"));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[test]
+fn no_config_anywhere_is_not_found_error() {
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("config_find_nothing");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(&tmp_dir_path).unwrap();
+
+    let err = Config::find_from(&tmp_dir_path, None).unwrap_err();
+
+    assert!(matches!(
+        err,
+        JobRunnerError::ConfigNotFoundError {
+            user_config: None,
+            ..
+        }
+    ));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
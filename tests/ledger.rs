@@ -0,0 +1,120 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    self, notification::parse_notifications, report::JobStats, step_runner::shell,
+    CloudServiceProvider, Job, Ledger, NotifyHooks, RunOptions,
+};
+use std::{fs, path::Path};
+
+#[tokio::test]
+async fn job_skips_objects_that_were_already_processed() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ledger");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let path_to_local_outputs = tmp_dir_path.join("bar");
+    let job = Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: tmp_dir_path.join("foo"),
+        path_to_local_outputs: path_to_local_outputs.clone(),
+        path_to_remote_outputs: "bar".into(),
+        steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    };
+    let bucket = common::DummyBucket {};
+    let step_runner = shell::Runner {};
+    let mut ledger = Ledger::open(&tmp_dir_path.join("ledger.jsonl"))
+        .expect("Something went wrong opening the ledger");
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut ledger,
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+    fs::remove_dir_all(&path_to_local_outputs)
+        .expect("Something went wrong removing the job's outputs");
+
+    // Act
+
+    let mut ledger = Ledger::open(&tmp_dir_path.join("ledger.jsonl"))
+        .expect("Something went wrong reopening the ledger");
+    let mut stats = JobStats::default();
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut ledger,
+        &mut stats,
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong rerunning the job");
+
+    // Assert
+
+    assert!(!path_to_local_outputs.exists());
+    assert_eq!(stats.objects_downloaded, 0);
+    assert_eq!(stats.objects_skipped, 1);
+    assert!(stats.skipped);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn job_reprocesses_s3_object_that_was_overwritten() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("ledger_s3_overwritten");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "in".into(),
+        path_to_local_inputs: tmp_dir_path.join("foo"),
+        path_to_local_outputs: tmp_dir_path.join("bar"),
+        path_to_remote_outputs: "out".into(),
+        steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    };
+    let bucket = common::MemoryBucket::with_objects(&["in/a.png"]);
+    let step_runner = shell::Runner {};
+    let mut ledger = Ledger::open(&tmp_dir_path.join("ledger.jsonl"))
+        .expect("Something went wrong opening the ledger");
+    let body = r#"{"Records":[
+        {"eventName":"ObjectCreated:Put","s3":{"bucket":{"name":"foo"},"object":{"key":"in/a.png","sequencer":"0055AED6DCD90281E5"}}},
+        {"eventName":"ObjectCreated:Put","s3":{"bucket":{"name":"foo"},"object":{"key":"in/a.png","sequencer":"0055AED6DCD90281E6"}}}
+    ]}"#;
+    let notifications =
+        parse_notifications(body).expect("Something went wrong parsing the notification");
+
+    // Act
+
+    let mut stats = JobStats::default();
+    for notification in &notifications {
+        job.run_on_objects(
+            &bucket,
+            &step_runner,
+            std::slice::from_ref(&notification.object),
+            &mut ledger,
+            &mut stats,
+            &RunOptions::default(),
+        )
+        .await
+        .expect("Something went wrong running the job");
+    }
+
+    // Assert
+
+    assert_eq!(stats.objects_downloaded, 2);
+    assert_eq!(stats.objects_skipped, 0);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
@@ -0,0 +1,53 @@
+#![cfg(unix)]
+
+mod common;
+
+use cloud_storage_job_runner::{
+    report::JobStats, step_runner::shell, CloudServiceProvider, Job, Ledger, NotifyHooks,
+    RunOptions,
+};
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt, path::Path};
+
+#[tokio::test]
+async fn local_paths_do_not_have_to_be_unicode() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("non_unicode");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(&tmp_dir_path).unwrap();
+    let path_to_local_inputs = tmp_dir_path.join(OsStr::from_bytes(b"caf\xe9 inputs"));
+    let path_to_local_outputs = tmp_dir_path.join(OsStr::from_bytes(b"caf\xe9 outputs"));
+    let job = Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: path_to_local_inputs.clone(),
+        path_to_local_outputs: path_to_local_outputs.clone(),
+        path_to_remote_outputs: "bar".into(),
+        steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    };
+    let bucket = common::DummyBucket {};
+    let step_runner = shell::Runner {};
+
+    // Act
+
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    assert!(path_to_local_inputs.join("foo.txt").exists());
+    assert!(path_to_local_outputs.join("foo.txt").exists());
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
@@ -0,0 +1,123 @@
+use chrono::Utc;
+use cloud_storage_job_runner::{
+    report::{JobReport, JobStats},
+    NotifyHooks,
+};
+use serde_json::Value;
+use std::{fs, path::Path, time::Duration};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+/// Accepts one HTTP request on `listener`, responds to it with an empty 200,
+/// and returns its body.
+fn receive_one_request(listener: TcpListener) -> JoinHandle<String> {
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        stream.read_exact(&mut body).await.unwrap();
+        stream
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    })
+}
+
+#[tokio::test]
+async fn webhook_receives_summary_of_run() {
+    // Arrange
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let request = receive_one_request(listener);
+    let hooks = NotifyHooks {
+        webhook: Some(url),
+        ..Default::default()
+    };
+    let report = JobReport::new(
+        "resize",
+        Utc::now(),
+        Duration::from_millis(1500),
+        JobStats {
+            objects_downloaded: 1,
+            remote_outputs: Some("results/20220418T020000Z".into()),
+            ..Default::default()
+        },
+        &Ok(()),
+    );
+
+    // Act
+
+    hooks
+        .run(&report)
+        .await
+        .expect("Something went wrong sending the webhook");
+
+    // Assert
+
+    let body: Value = serde_json::from_str(&request.await.unwrap()).unwrap();
+    assert_eq!(body["job"], "resize");
+    assert_eq!(body["status"], "succeeded");
+    assert_eq!(body["duration_ms"], 1500);
+    assert_eq!(body["error"], Value::Null);
+    assert_eq!(body["output_prefix"], "results/20220418T020000Z");
+    assert!(body["text"].as_str().unwrap().contains("resize succeeded"));
+}
+
+#[tokio::test]
+async fn only_the_matching_command_runs() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("notify_hooks");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(&tmp_dir_path).unwrap();
+    let succeeded_marker = tmp_dir_path.join("succeeded");
+    let failed_marker = tmp_dir_path.join("failed");
+    let hooks = NotifyHooks {
+        on_success: Some(format!("touch {}", succeeded_marker.display())),
+        on_failure: Some(format!("touch {}", failed_marker.display())),
+        ..Default::default()
+    };
+    let report = JobReport::new(
+        "resize",
+        Utc::now(),
+        Duration::from_secs(1),
+        JobStats {
+            objects_downloaded: 1,
+            ..Default::default()
+        },
+        &Ok(()),
+    );
+
+    // Act
+
+    hooks
+        .run(&report)
+        .await
+        .expect("Something went wrong running the on_success command");
+
+    // Assert
+
+    assert!(succeeded_marker.exists());
+    assert!(!failed_marker.exists());
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
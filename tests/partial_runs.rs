@@ -0,0 +1,140 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    bucket::Object, report::JobStats, step_runner::shell, CloudServiceProvider, Job,
+    JobRunnerError, Ledger, NotifyHooks, RunOptions,
+};
+use std::{fs, path::Path};
+
+fn job(tmp_dir_path: &Path) -> Job {
+    Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: tmp_dir_path.join("inputs"),
+        path_to_local_outputs: tmp_dir_path.join("outputs"),
+        path_to_remote_outputs: "bar".into(),
+        steps: vec![
+            "mkdir -p [path_to_local_outputs]".into(),
+            "cp -r [path_to_local_inputs] [path_to_local_outputs]/copied".into(),
+            "touch [path_to_local_outputs]/third.txt".into(),
+        ],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    }
+}
+
+#[tokio::test]
+async fn resume_from_step_on_downloaded_inputs() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("partial_resume");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(tmp_dir_path.join("inputs")).unwrap();
+    fs::create_dir_all(tmp_dir_path.join("outputs")).unwrap();
+    fs::write(tmp_dir_path.join("inputs/local.txt"), "Already downloaded").unwrap();
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::with_objects(&["foo/remote.txt"]);
+    let mut ledger = Ledger::default();
+    let mut stats = JobStats::default();
+
+    // Act
+
+    job.run(
+        &bucket,
+        &shell::Runner {},
+        &mut ledger,
+        &mut stats,
+        &RunOptions {
+            from_step: Some(2),
+            to_step: Some(2),
+            skip_download: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    assert!(!tmp_dir_path.join("inputs/remote.txt").exists());
+    assert!(!tmp_dir_path.join("outputs/third.txt").exists());
+    assert_eq!(stats.step_durations.len(), 1);
+    assert_eq!(stats.step_durations[0].0, 1);
+    assert_eq!(
+        bucket.object_names(),
+        vec!["bar/copied/local.txt", "foo/remote.txt"]
+    );
+    assert!(!ledger.contains(&Object {
+        name: "foo/remote.txt".into(),
+        generation: 1,
+    }));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn skip_upload_leaves_bucket_alone() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("partial_skip_upload");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::with_objects(&["foo/remote.txt"]);
+
+    // Act
+
+    job.run(
+        &bucket,
+        &shell::Runner {},
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions {
+            skip_upload: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    assert!(tmp_dir_path.join("outputs/copied/remote.txt").exists());
+    assert!(tmp_dir_path.join("outputs/third.txt").exists());
+    assert_eq!(bucket.object_names(), vec!["foo/remote.txt"]);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn step_past_the_last_one_is_error() {
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("partial_past_last_step");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::with_objects(&["foo/remote.txt"]);
+
+    let err = job
+        .run(
+            &bucket,
+            &shell::Runner {},
+            &mut Ledger::default(),
+            &mut JobStats::default(),
+            &RunOptions {
+                from_step: Some(4),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        JobRunnerError::InvalidStepRangeError {
+            from_step: 4,
+            to_step: 3,
+            num_steps: 3,
+        }
+    ));
+    assert!(!tmp_dir_path.join("inputs").exists());
+}
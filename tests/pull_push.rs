@@ -0,0 +1,109 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    report::JobStats, CloudServiceProvider, Job, JobRunnerError, NotifyHooks,
+};
+use std::{fs, path::Path};
+
+fn job(tmp_dir_path: &Path) -> Job {
+    Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: tmp_dir_path.join("inputs"),
+        path_to_local_outputs: tmp_dir_path.join("outputs"),
+        path_to_remote_outputs: "bar".into(),
+        steps: vec!["false".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    }
+}
+
+#[tokio::test]
+async fn pull_downloads_inputs_without_running_steps() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("pull");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = job(&tmp_dir_path);
+    let bucket =
+        common::MemoryBucket::with_objects(&["foo/a.txt", "foo/nested/b.txt", "other/c.txt"]);
+    let mut stats = JobStats::default();
+
+    // Act
+
+    job.pull(&bucket, &mut stats)
+        .await
+        .expect("Something went wrong pulling the job's inputs");
+
+    // Assert
+
+    assert!(tmp_dir_path.join("inputs/a.txt").exists());
+    assert!(tmp_dir_path.join("inputs/nested/b.txt").exists());
+    assert!(!tmp_dir_path.join("inputs/c.txt").exists());
+    assert!(!tmp_dir_path.join("outputs").exists());
+    assert_eq!(stats.objects_downloaded, 2);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn push_uploads_outputs_without_running_steps() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("push");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    fs::create_dir_all(tmp_dir_path.join("outputs/nested")).unwrap();
+    fs::write(tmp_dir_path.join("outputs/nested/result.txt"), "done").unwrap();
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+
+    // Act
+
+    job.push(&bucket, &mut JobStats::default())
+        .await
+        .expect("Something went wrong pushing the job's outputs");
+
+    // Assert
+
+    assert_eq!(
+        bucket.object_names(),
+        vec!["bar/nested/result.txt", "foo/a.txt"]
+    );
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn push_without_local_outputs_is_error() {
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("push_without_outputs");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::default();
+
+    let err = job
+        .push(&bucket, &mut JobStats::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, JobRunnerError::IOError(_)));
+    assert!(bucket.object_names().is_empty());
+}
+
+#[tokio::test]
+async fn pull_of_object_that_escapes_local_inputs_is_error() {
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("pull_escaping_object");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = job(&tmp_dir_path);
+    let bucket = common::MemoryBucket::with_objects(&["foo/../escaped.txt"]);
+
+    let err = job
+        .pull(&bucket, &mut JobStats::default())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, JobRunnerError::InvalidObjectNameError { .. }));
+    assert!(!tmp_dir_path.join("escaped.txt").exists());
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+}
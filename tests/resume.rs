@@ -0,0 +1,142 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    bucket::Object, report::JobStats, step_runner::shell, Checkpoint, CloudServiceProvider, Job,
+    Ledger, NotifyHooks, RunOptions,
+};
+use std::{fs, path::Path};
+
+#[tokio::test]
+async fn resumed_run_picks_up_where_it_failed() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("resume");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let path_to_runs = tmp_dir_path.join("runs");
+    let job = Job {
+        cloud_service_provider: CloudServiceProvider::GCP,
+        bucket_name: "foo".into(),
+        path_to_remote_inputs: "foo".into(),
+        path_to_local_inputs: tmp_dir_path.join("inputs"),
+        path_to_local_outputs: tmp_dir_path.join("outputs"),
+        path_to_remote_outputs: "bar".into(),
+        steps: vec![
+            // Fails if it's run again, since the directory already exists.
+            "mkdir [path_to_local_outputs]".into(),
+            // Fails until the test creates the file it reads.
+            format!("cat {}", tmp_dir_path.join("network-is-back").display()),
+            "cp -r [path_to_local_inputs] [path_to_local_outputs]/copied".into(),
+        ],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
+    };
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+    let mut ledger = Ledger::default();
+    let mut checkpoint = Checkpoint::create(&path_to_runs, "resume");
+    job.run_with_checkpoint(
+        &bucket,
+        &shell::Runner {},
+        &mut ledger,
+        &mut checkpoint,
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect_err("The job's second step should have failed");
+    // If resuming downloads this object again, this gets overwritten.
+    fs::write(tmp_dir_path.join("inputs/a.txt"), "Downloaded before").unwrap();
+    fs::write(tmp_dir_path.join("network-is-back"), "").unwrap();
+    let mut checkpoint = Checkpoint::open(&path_to_runs, checkpoint.run_id())
+        .expect("Something went wrong reopening the run's checkpoint");
+    let mut stats = JobStats::default();
+
+    // Act
+
+    job.run_with_checkpoint(
+        &bucket,
+        &shell::Runner {},
+        &mut ledger,
+        &mut checkpoint,
+        &mut stats,
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong resuming the job");
+
+    // Assert
+
+    assert_eq!(stats.objects_downloaded, 0);
+    assert_eq!(
+        stats
+            .step_durations
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(
+        fs::read_to_string(tmp_dir_path.join("outputs/copied/a.txt")).unwrap(),
+        "Downloaded before"
+    );
+    assert_eq!(bucket.object_names(), vec!["bar/copied/a.txt", "foo/a.txt"]);
+    assert!(ledger.contains(&Object {
+        name: "foo/a.txt".into(),
+        generation: 1,
+    }));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn run_of_job_with_slash_in_its_name_can_be_resumed() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("resume_slash");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let path_to_runs = tmp_dir_path.join("runs");
+    let job = Job {
+        // Fails until the test creates the file it reads.
+        steps: vec![format!(
+            "cat {}",
+            tmp_dir_path.join("network-is-back").display()
+        )],
+        ..common::job(&tmp_dir_path, "bar")
+    };
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+    let mut ledger = Ledger::default();
+    let mut checkpoint = Checkpoint::create(&path_to_runs, "images/../resize");
+    job.run_with_checkpoint(
+        &bucket,
+        &shell::Runner {},
+        &mut ledger,
+        &mut checkpoint,
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect_err("The job's step should have failed");
+    fs::write(tmp_dir_path.join("network-is-back"), "").unwrap();
+
+    // Act
+
+    let mut checkpoint = Checkpoint::open(&path_to_runs, checkpoint.run_id())
+        .expect("Something went wrong reopening the run's checkpoint");
+    job.run_with_checkpoint(
+        &bucket,
+        &shell::Runner {},
+        &mut ledger,
+        &mut checkpoint,
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong resuming the job");
+
+    // Assert
+
+    assert_eq!(checkpoint.job(), "images/../resize");
+    assert!(checkpoint.is_step_completed(0));
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
@@ -1,6 +1,9 @@
 mod common;
 
-use cloud_storage_job_runner::{self, step_runner::shell, CloudServiceProvider, Job};
+use cloud_storage_job_runner::{
+    self, report::JobStats, step_runner::shell, CloudServiceProvider, Job, Ledger, NotifyHooks,
+    RunOptions,
+};
 use std::fs;
 
 #[tokio::test]
@@ -18,15 +21,24 @@ async fn shell_step_runner_write_new_files_to_disk() {
         path_to_local_outputs: format!("{}/bar", tmp_dir_path).into(),
         path_to_remote_outputs: "bar".into(),
         steps: vec!["cp -r [path_to_local_inputs] [path_to_local_outputs]".into()],
+        schedule: None,
+        atomic_outputs: false,
+        notify: NotifyHooks::default(),
     };
     let bucket = common::DummyBucket {};
     let step_runner = shell::Runner {};
 
     // Act
 
-    job.run(&bucket, &step_runner)
-        .await
-        .expect("Something went wrong running the job");
+    job.run(
+        &bucket,
+        &step_runner,
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
 
     // Assert
 
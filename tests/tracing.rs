@@ -0,0 +1,175 @@
+mod common;
+
+use cloud_storage_job_runner::{
+    report::JobStats, step_runner::shell, Config, JobRunner, Ledger, RunOptions,
+};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Metadata, Subscriber,
+};
+
+/// A span that was created, along with the fields it was created with.
+#[derive(Debug)]
+struct RecordedSpan {
+    name: &'static str,
+    fields: HashMap<String, String>,
+}
+
+/// Implementation of a [Subscriber] that keeps every span it sees in memory,
+/// so tests can check which spans were created, and with which fields.
+#[derive(Default, Clone)]
+struct RecordingSubscriber {
+    next_id: Arc<AtomicU64>,
+    spans: Arc<Mutex<Vec<RecordedSpan>>>,
+}
+
+impl RecordingSubscriber {
+    /// Returns the fields of each span named `name`, in the order they were
+    /// created.
+    fn spans_named(&self, name: &str) -> Vec<HashMap<String, String>> {
+        self.spans
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|span| span.name == name)
+            .map(|span| span.fields.clone())
+            .collect()
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name().into(), format!("{value:?}"));
+    }
+}
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = HashMap::new();
+        span.record(&mut FieldVisitor(&mut fields));
+        self.spans.lock().unwrap().push(RecordedSpan {
+            name: span.metadata().name(),
+            fields,
+        });
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut spans = self.spans.lock().unwrap();
+        let span = &mut spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[tokio::test]
+async fn job_steps_and_transfers_are_traced() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("tracing");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let config = Config {
+        jobs: HashMap::from([("copy".into(), common::job(&tmp_dir_path, "bar"))]),
+    };
+    let mut job_runner = JobRunner::new(
+        &config,
+        RunOptions {
+            path_to_ledgers: tmp_dir_path.join("ledger"),
+            path_to_runs: tmp_dir_path.join("runs"),
+            ..Default::default()
+        },
+    );
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+    let subscriber = RecordingSubscriber::default();
+    // Tests run on a single thread, so everything the job does is seen.
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    // Act
+
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling for new objects");
+
+    // Assert
+
+    let jobs = subscriber.spans_named("job");
+    assert_eq!(jobs.len(), 1);
+    assert_eq!(jobs[0]["job"], "copy");
+    let steps = subscriber.spans_named("step");
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0]["index"], "0");
+    assert!(steps[0]["step"].starts_with("cp -r"));
+    let downloads = subscriber.spans_named("download_objects");
+    assert_eq!(downloads.len(), 1);
+    assert_eq!(downloads[0]["num_objects"], "1");
+    let uploads = subscriber.spans_named("run_steps_and_upload");
+    assert_eq!(uploads.len(), 1);
+    assert_eq!(uploads[0]["remote_outputs"], "bar");
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn runs_are_traced_with_their_bucket() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("tracing_bucket");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let job = common::job(&tmp_dir_path, "bar");
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt", "foo/b.txt"]);
+    let subscriber = RecordingSubscriber::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    // Act
+
+    job.run(
+        &bucket,
+        &shell::Runner {},
+        &mut Ledger::default(),
+        &mut JobStats::default(),
+        &RunOptions::default(),
+    )
+    .await
+    .expect("Something went wrong running the job");
+
+    // Assert
+
+    let runs = subscriber.spans_named("run_with_checkpoint");
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0]["bucket"], "foo");
+    assert_eq!(
+        subscriber.spans_named("download_objects")[0]["num_objects"],
+        "2"
+    );
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
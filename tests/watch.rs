@@ -0,0 +1,170 @@
+mod common;
+
+use cloud_storage_job_runner::{report::JobStatus, Config, JobRunner, RunOptions};
+use std::{collections::HashMap, fs, path::Path};
+
+fn config(tmp_dir_path: &Path) -> Config {
+    Config {
+        jobs: HashMap::from([("copy".into(), common::job(tmp_dir_path, "bar"))]),
+    }
+}
+
+fn options(tmp_dir_path: &Path) -> RunOptions {
+    RunOptions {
+        path_to_ledgers: tmp_dir_path.join("ledger"),
+        path_to_runs: tmp_dir_path.join("runs"),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn poll_runs_job_on_new_objects() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch_new");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let config = config(&tmp_dir_path);
+    let mut job_runner = JobRunner::new(&config, options(&tmp_dir_path));
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+
+    // Act
+
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling for new objects");
+
+    // Assert
+
+    let report = job_runner.report();
+    assert_eq!(report.jobs.len(), 1);
+    assert_eq!(report.jobs[0].status, JobStatus::Succeeded);
+    assert_eq!(report.jobs[0].stats.objects_downloaded, 1);
+    assert_eq!(report.jobs[0].stats.objects_uploaded, 1);
+    assert_eq!(bucket.object_names(), vec!["bar/a.txt", "foo/a.txt"]);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn poll_ignores_unchanged_objects() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch_unchanged");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let config = config(&tmp_dir_path);
+    let mut job_runner = JobRunner::new(&config, options(&tmp_dir_path));
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling for new objects");
+
+    // Act
+
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling again");
+
+    // Assert
+
+    assert_eq!(job_runner.report().jobs.len(), 1);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn poll_reruns_job_on_reuploaded_objects() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch_reuploaded");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let config = config(&tmp_dir_path);
+    let mut job_runner = JobRunner::new(&config, options(&tmp_dir_path));
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt", "foo/b.txt"]);
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling for new objects");
+    bucket.insert("foo/a.txt", b"New contents".to_vec());
+
+    // Act
+
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling again");
+
+    // Assert
+
+    let report = job_runner.report();
+    assert_eq!(report.jobs.len(), 2);
+    assert_eq!(report.jobs[1].status, JobStatus::Succeeded);
+    assert_eq!(report.jobs[1].stats.objects_downloaded, 1);
+    assert_eq!(
+        fs::read_to_string(tmp_dir_path.join("inputs/a.txt")).unwrap(),
+        "New contents"
+    );
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn poll_ignores_objects_that_existed_when_watching_started() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch_skip_existing");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let config = config(&tmp_dir_path);
+    let mut job_runner = JobRunner::new(&config, options(&tmp_dir_path));
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+    job_runner
+        .skip_existing("copy", &bucket)
+        .await
+        .expect("Something went wrong skipping existing objects");
+
+    // Act
+
+    job_runner
+        .poll("copy", &bucket)
+        .await
+        .expect("Something went wrong polling for new objects");
+
+    // Assert
+
+    assert!(job_runner.report().jobs.is_empty());
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}
+
+#[tokio::test]
+async fn failed_polls_do_not_leave_checkpoints_behind() {
+    // Arrange
+
+    let tmp_dir_path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("watch_failed");
+    let _ = fs::remove_dir_all(&tmp_dir_path);
+    let mut config = config(&tmp_dir_path);
+    config.jobs.get_mut("copy").unwrap().steps = vec!["false".into()];
+    let mut job_runner = JobRunner::new(&config, options(&tmp_dir_path));
+    let bucket = common::MemoryBucket::with_objects(&["foo/a.txt"]);
+
+    // Act
+
+    for _ in 0..2 {
+        job_runner
+            .poll("copy", &bucket)
+            .await
+            .expect_err("The job's step should have failed");
+    }
+
+    // Assert
+
+    assert_eq!(job_runner.report().jobs.len(), 2);
+    let checkpoints = fs::read_dir(tmp_dir_path.join("runs"))
+        .map(|entries| entries.count())
+        .unwrap_or_default();
+    assert!(checkpoints <= 1);
+    fs::remove_dir_all(&tmp_dir_path)
+        .expect("Something went wrong removing the temp folder after running a test");
+}